@@ -2,7 +2,7 @@ import React from 'react'
 import styles from './style.module.css'
 import { Utils } from '../../../shared/utils'
 import { Spacer } from '../../atoms/spacer'
-import { crowdfund } from '../../../shared/contracts'
+import { campaignId, crowdfund } from '../../../shared/contracts'
 
 export interface IDepositsProps {
   address: string
@@ -15,7 +15,7 @@ export function Deposits(props: IDepositsProps) {
   const [balance, setBalance] = React.useState<BigInt>(BigInt(0))
 
   React.useEffect(() => {
-    crowdfund.balance({ user: props.address }).then(setBalance)
+    crowdfund.balance({ campaign_id: campaignId, user: props.address }).then(setBalance)
   }, [props.address])
 
 
@@ -4,7 +4,7 @@ import { TransactionModal } from '../../molecules/transaction-modal'
 import { Utils } from '../../../shared/utils'
 import styles from './style.module.css'
 import { Spacer } from '../../atoms/spacer'
-import { abundance, crowdfund } from '../../../shared/contracts'
+import { abundance, campaignId, crowdfund } from '../../../shared/contracts'
 
 export interface IFormPledgeProps {
   account: string
@@ -78,6 +78,7 @@ const FormPledge: FunctionComponent<IFormPledgeProps> = props => {
 
     try {
       await crowdfund.deposit({
+        campaign_id: campaignId,
         user: props.account,
         amount: BigInt(amount * 10 ** decimals),
       })
@@ -10,6 +10,7 @@ import {
 import {
   crowdfund as crowdfundContract,
   abundance as abundanceContract,
+  campaignId,
 } from '../../../shared/contracts'
 
 import * as SorobanClient from 'soroban-client'
@@ -34,13 +35,13 @@ const Pledge: FunctionComponent = () => {
 
   React.useEffect(() => {
     Promise.all([
-      abundanceContract.balance({ id: crowdfundContract.options.contractId }),
+      crowdfundContract.total_pledged({ campaign_id: campaignId }),
       abundanceContract.decimals(),
       abundanceContract.name(),
       abundanceContract.symbol(),
 
-      crowdfundContract.deadline(),
-      crowdfundContract.target(),
+      crowdfundContract.deadline({ campaign_id: campaignId }),
+      crowdfundContract.target({ campaign_id: campaignId }),
     ]).then(fetched => {
       setAbundance({
         balance: fetched[0],
@@ -50,7 +51,8 @@ const Pledge: FunctionComponent = () => {
       })
 
       setCrowdfund({
-        deadline: new Date(Number(fetched[4]) * 1000),
+        // Campaign 0 is set up by initialize, which always takes a timestamp
+        deadline: new Date(Number(fetched[4].values[0]) * 1000),
         target: fetched[5],
       })
     })
@@ -3,16 +3,17 @@
 use crate::admin::{has_administrator, read_administrator, write_administrator};
 use crate::allowance::{read_allowance, spend_allowance, write_allowance};
 use crate::balance::{read_balance, receive_balance, spend_balance};
+use crate::errors::TokenError;
 use crate::metadata::{read_decimal, read_name, read_symbol, write_metadata};
 use crate::storage_types::{INSTANCE_BUMP_AMOUNT, INSTANCE_LIFETIME_THRESHOLD};
 use soroban_sdk::token::{self, Interface as _};
-use soroban_sdk::{contract, contractimpl, Address, Env, String};
+use soroban_sdk::{contract, contractimpl, panic_with_error, Address, Env, String};
 use soroban_token_sdk::metadata::TokenMetadata;
 use soroban_token_sdk::TokenUtils;
 
-fn check_nonnegative_amount(amount: i128) {
+fn check_nonnegative_amount(e: &Env, amount: i128) {
     if amount < 0 {
-        panic!("negative amount is not allowed: {}", amount)
+        panic_with_error!(e, TokenError::NegativeAmountError)
     }
 }
 
@@ -23,11 +24,11 @@ pub struct Token;
 impl Token {
     pub fn initialize(e: Env, admin: Address, decimal: u32, name: String, symbol: String) {
         if has_administrator(&e) {
-            panic!("already initialized")
+            panic_with_error!(&e, TokenError::AlreadyInitializedError)
         }
         write_administrator(&e, &admin);
         if decimal > u8::MAX.into() {
-            panic!("Decimal must fit in a u8");
+            panic_with_error!(&e, TokenError::DecimalError);
         }
 
         write_metadata(
@@ -48,7 +49,7 @@ impl Token {
     /// account
     /// * `amount` - The amount of tokens to mint (remember to multiply by `decimals`!)
     pub fn mint(e: Env, to: Address, amount: i128) {
-        check_nonnegative_amount(amount);
+        check_nonnegative_amount(&e, amount);
         to.require_auth();
 
         e.storage()
@@ -84,7 +85,7 @@ impl token::Interface for Token {
     fn approve(e: Env, from: Address, spender: Address, amount: i128, expiration_ledger: u32) {
         from.require_auth();
 
-        check_nonnegative_amount(amount);
+        check_nonnegative_amount(&e, amount);
 
         e.storage()
             .instance()
@@ -113,7 +114,7 @@ impl token::Interface for Token {
     fn transfer(e: Env, from: Address, to: Address, amount: i128) {
         from.require_auth();
 
-        check_nonnegative_amount(amount);
+        check_nonnegative_amount(&e, amount);
 
         e.storage()
             .instance()
@@ -127,7 +128,7 @@ impl token::Interface for Token {
     fn transfer_from(e: Env, spender: Address, from: Address, to: Address, amount: i128) {
         spender.require_auth();
 
-        check_nonnegative_amount(amount);
+        check_nonnegative_amount(&e, amount);
 
         e.storage()
             .instance()
@@ -142,7 +143,7 @@ impl token::Interface for Token {
     fn burn(e: Env, from: Address, amount: i128) {
         from.require_auth();
 
-        check_nonnegative_amount(amount);
+        check_nonnegative_amount(&e, amount);
 
         e.storage()
             .instance()
@@ -155,7 +156,7 @@ impl token::Interface for Token {
     fn burn_from(e: Env, spender: Address, from: Address, amount: i128) {
         spender.require_auth();
 
-        check_nonnegative_amount(amount);
+        check_nonnegative_amount(&e, amount);
 
         e.storage()
             .instance()
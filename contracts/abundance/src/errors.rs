@@ -0,0 +1,16 @@
+use soroban_sdk::contracterror;
+
+/// Errors raised by the token. Codes 1-99 match the errors of the Stellar
+/// Asset Contract with the same meaning; codes from 100 are specific to this
+/// token.
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum TokenError {
+    AlreadyInitializedError = 3,
+    NegativeAmountError = 8,
+    AllowanceError = 9,
+    BalanceError = 10,
+    OverflowError = 12,
+    DecimalError = 100,
+}
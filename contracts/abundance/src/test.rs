@@ -4,7 +4,7 @@ extern crate std;
 use crate::{contract::Token, TokenClient};
 use soroban_sdk::{
     symbol_short,
-    testutils::{Address as _, AuthorizedFunction, AuthorizedInvocation},
+    testutils::{Address as _, AuthorizedFunction, AuthorizedInvocation, Ledger},
     Address, Env, IntoVal, Symbol,
 };
 
@@ -194,7 +194,7 @@ fn test_burn() {
 }
 
 #[test]
-#[should_panic(expected = "insufficient balance")]
+#[should_panic(expected = "Error(Contract, #10)")]
 fn transfer_insufficient_balance() {
     let e = Env::default();
     e.mock_all_auths();
@@ -211,7 +211,7 @@ fn transfer_insufficient_balance() {
 }
 
 #[test]
-#[should_panic(expected = "insufficient allowance")]
+#[should_panic(expected = "Error(Contract, #9)")]
 fn transfer_from_insufficient_allowance() {
     let e = Env::default();
     e.mock_all_auths();
@@ -232,7 +232,37 @@ fn transfer_from_insufficient_allowance() {
 }
 
 #[test]
-#[should_panic(expected = "already initialized")]
+#[should_panic(expected = "Error(Contract, #8)")]
+fn transfer_negative_amount() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::random(&e);
+    let user1 = Address::random(&e);
+    let user2 = Address::random(&e);
+    let token = create_token(&e, &admin);
+
+    token.mint(&user1, &1000);
+    token.transfer(&user1, &user2, &-1);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #9)")]
+fn approve_expired() {
+    let e = Env::default();
+    e.mock_all_auths();
+    e.ledger().with_mut(|l| l.sequence_number = 100);
+
+    let admin = Address::random(&e);
+    let user1 = Address::random(&e);
+    let user2 = Address::random(&e);
+    let token = create_token(&e, &admin);
+
+    token.approve(&user1, &user2, &500, &99);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #3)")]
 fn initialize_already_initialized() {
     let e = Env::default();
     let admin = Address::random(&e);
@@ -242,7 +272,7 @@ fn initialize_already_initialized() {
 }
 
 #[test]
-#[should_panic(expected = "Decimal must fit in a u8")]
+#[should_panic(expected = "Error(Contract, #100)")]
 fn decimal_is_over_max() {
     let e = Env::default();
     let admin = Address::random(&e);
@@ -0,0 +1,6 @@
+use soroban_sdk::{Address, Env, Symbol};
+
+pub(crate) fn campaign_deployed(e: &Env, index: u64, creator: Address, campaign: Address) {
+    let topics = (Symbol::new(e, "campaign_deployed"), creator);
+    e.events().publish(topics, (index, campaign));
+}
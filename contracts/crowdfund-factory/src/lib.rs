@@ -0,0 +1,277 @@
+#![no_std]
+use soroban_sdk::{
+    contract, contractimpl, contractmeta, contracttype, xdr::ToXdr, Address, Bytes, BytesN, Env,
+    IntoVal, Symbol, Val, Vec,
+};
+
+mod events;
+mod test;
+
+pub(crate) const DAY_IN_LEDGERS: u32 = 17280;
+pub(crate) const CAMPAIGN_BUMP_AMOUNT: u32 = 30 * DAY_IN_LEDGERS;
+pub(crate) const CAMPAIGN_LIFETIME_THRESHOLD: u32 = CAMPAIGN_BUMP_AMOUNT - DAY_IN_LEDGERS;
+pub(crate) const NONCE_BUMP_AMOUNT: u32 = 30 * DAY_IN_LEDGERS;
+pub(crate) const NONCE_LIFETIME_THRESHOLD: u32 = NONCE_BUMP_AMOUNT - DAY_IN_LEDGERS;
+
+#[derive(Clone)]
+#[contracttype]
+pub enum DataKey {
+    Admin,
+    WasmHash,
+    CampaignCount,
+    Campaign(u64),
+    Nonce(Address),
+    Fee,
+}
+
+/// The platform fee every crowdfund the factory deploys takes from its
+/// recipient's claim. Mirrors the crowdfund contract's `Fee`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct Fee {
+    pub fee_bps: u32,
+    pub treasury: Address,
+}
+
+/// A crowdfund deployed by the factory, as listed in its registry.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct Campaign {
+    pub address: Address,
+    pub creator: Address,
+    pub token: Address,
+    pub target: i128,
+    pub deadline: u64,
+}
+
+fn get_admin(e: &Env) -> Address {
+    e.storage()
+        .instance()
+        .get::<_, Address>(&DataKey::Admin)
+        .expect("not initialized")
+}
+
+fn get_wasm_hash(e: &Env) -> BytesN<32> {
+    e.storage()
+        .instance()
+        .get::<_, BytesN<32>>(&DataKey::WasmHash)
+        .expect("not initialized")
+}
+
+fn get_fee(e: &Env) -> Option<Fee> {
+    e.storage().instance().get::<_, Fee>(&DataKey::Fee)
+}
+
+fn get_campaign_count(e: &Env) -> u64 {
+    e.storage()
+        .instance()
+        .get::<_, u64>(&DataKey::CampaignCount)
+        .unwrap_or(0)
+}
+
+fn get_nonce(e: &Env, creator: &Address) -> u64 {
+    let key = DataKey::Nonce(creator.clone());
+    if let Some(nonce) = e.storage().persistent().get::<_, u64>(&key) {
+        e.storage()
+            .persistent()
+            .bump(&key, NONCE_LIFETIME_THRESHOLD, NONCE_BUMP_AMOUNT);
+        nonce
+    } else {
+        0
+    }
+}
+
+fn set_nonce(e: &Env, creator: &Address, nonce: u64) {
+    let key = DataKey::Nonce(creator.clone());
+    e.storage().persistent().set(&key, &nonce);
+    e.storage()
+        .persistent()
+        .bump(&key, NONCE_LIFETIME_THRESHOLD, NONCE_BUMP_AMOUNT);
+}
+
+fn get_campaign(e: &Env, index: u64) -> Campaign {
+    let key = DataKey::Campaign(index);
+    let campaign = e
+        .storage()
+        .persistent()
+        .get::<_, Campaign>(&key)
+        .expect("campaign not found");
+    e.storage()
+        .persistent()
+        .bump(&key, CAMPAIGN_LIFETIME_THRESHOLD, CAMPAIGN_BUMP_AMOUNT);
+    campaign
+}
+
+fn add_campaign(e: &Env, campaign: &Campaign) -> u64 {
+    let index = get_campaign_count(e);
+    let key = DataKey::Campaign(index);
+    e.storage().persistent().set(&key, campaign);
+    e.storage()
+        .persistent()
+        .bump(&key, CAMPAIGN_LIFETIME_THRESHOLD, CAMPAIGN_BUMP_AMOUNT);
+    e.storage()
+        .instance()
+        .set(&DataKey::CampaignCount, &(index + 1));
+    index
+}
+
+// Each creator's deployments are salted with a per-creator nonce, so the
+// address of their next campaign is known in advance.
+fn get_salt(e: &Env, creator: &Address, nonce: u64) -> BytesN<32> {
+    let mut salt = Bytes::new(e);
+    salt.append(&creator.clone().to_xdr(e));
+    salt.append(&nonce.to_xdr(e));
+    e.crypto().sha256(&salt)
+}
+
+// Metadata that is added on to the WASM custom section
+contractmeta!(
+    key = "Description",
+    val = "Factory that deploys crowdfund contracts and keeps a registry of them"
+);
+
+#[contract]
+struct CrowdfundFactory;
+
+/*
+How to use this contract to deploy crowdfunds
+
+1. Upload the crowdfund WASM and call initialize(admin, wasm_hash, fee), with the fee the
+   deployed crowdfunds take, if any. Deploy and initialize the factory in one transaction
+   so nobody else can initialize it first.
+2. Call deploy(creator, recipient, deadline_unix_epoch, target_amount, token) to
+   deploy and initialize a new crowdfund.
+3. List the deployed crowdfunds with campaign_count() and campaigns(start, limit).
+4. The admin may point later deployments at a new crowdfund WASM with set_wasm_hash(wasm_hash),
+   and sweep_excess(index, token, to) tokens sent to a deployed crowdfund without being pledged.
+*/
+#[contractimpl]
+#[allow(clippy::needless_pass_by_value)]
+impl CrowdfundFactory {
+    pub fn initialize(e: Env, admin: Address, wasm_hash: BytesN<32>, fee: Option<Fee>) {
+        assert!(
+            !e.storage().instance().has(&DataKey::Admin),
+            "already initialized"
+        );
+        admin.require_auth();
+
+        e.storage().instance().set(&DataKey::Admin, &admin);
+        e.storage().instance().set(&DataKey::WasmHash, &wasm_hash);
+        if let Some(fee) = fee {
+            e.storage().instance().set(&DataKey::Fee, &fee);
+        }
+    }
+
+    pub fn fee(e: Env) -> Option<Fee> {
+        get_fee(&e)
+    }
+
+    pub fn admin(e: Env) -> Address {
+        get_admin(&e)
+    }
+
+    pub fn wasm_hash(e: Env) -> BytesN<32> {
+        get_wasm_hash(&e)
+    }
+
+    /// Deploy later crowdfunds from another uploaded WASM. Only the admin may
+    /// call this; crowdfunds already deployed are unaffected.
+    pub fn set_wasm_hash(e: Env, wasm_hash: BytesN<32>) {
+        get_admin(&e).require_auth();
+        e.storage().instance().set(&DataKey::WasmHash, &wasm_hash);
+    }
+
+    /// The number of deployments `creator` has made, which salts the next one.
+    pub fn nonce(e: Env, creator: Address) -> u64 {
+        get_nonce(&e, &creator)
+    }
+
+    /// The address `creator`'s next deployment will have.
+    pub fn next_address(e: Env, creator: Address) -> Address {
+        let salt = get_salt(&e, &creator, get_nonce(&e, &creator));
+        e.deployer().with_current_contract(salt).deployed_address()
+    }
+
+    /// Deploy a new crowdfund, initialize it and add it to the registry. Both
+    /// the creator and the recipient must authorize it.
+    pub fn deploy(
+        e: Env,
+        creator: Address,
+        recipient: Address,
+        deadline: u64,
+        target_amount: i128,
+        token: Address,
+    ) -> Address {
+        creator.require_auth();
+        // The crowdfund's initialize runs under the recipient's authorization
+        if recipient != creator {
+            recipient.require_auth();
+        }
+
+        let nonce = get_nonce(&e, &creator);
+        set_nonce(&e, &creator, nonce + 1);
+
+        let address = e
+            .deployer()
+            .with_current_contract(get_salt(&e, &creator, nonce))
+            .deploy(get_wasm_hash(&e));
+
+        // The factory administers every crowdfund it deploys
+        let init_args: Vec<Val> = (
+            recipient,
+            deadline,
+            target_amount,
+            token.clone(),
+            e.current_contract_address(),
+            get_fee(&e),
+        )
+            .into_val(&e);
+        e.invoke_contract::<()>(&address, &Symbol::new(&e, "initialize"), init_args);
+
+        let index = add_campaign(
+            &e,
+            &Campaign {
+                address: address.clone(),
+                creator: creator.clone(),
+                token,
+                target: target_amount,
+                deadline,
+            },
+        );
+
+        events::campaign_deployed(&e, index, creator, address.clone());
+        address
+    }
+
+    /// Recover `token`s sent to the crowdfund at `index` without being pledged.
+    /// Only the admin may call this. Returns the amount swept.
+    pub fn sweep_excess(e: Env, index: u64, token: Address, to: Address) -> i128 {
+        get_admin(&e).require_auth();
+
+        let sweep_args: Vec<Val> = (token, to).into_val(&e);
+        e.invoke_contract::<i128>(
+            &get_campaign(&e, index).address,
+            &Symbol::new(&e, "sweep_excess"),
+            sweep_args,
+        )
+    }
+
+    pub fn campaign_count(e: Env) -> u64 {
+        get_campaign_count(&e)
+    }
+
+    pub fn campaign(e: Env, index: u64) -> Campaign {
+        get_campaign(&e, index)
+    }
+
+    /// List up to `limit` campaigns in deployment order, starting at `start`.
+    pub fn campaigns(e: Env, start: u64, limit: u32) -> Vec<Campaign> {
+        let end = get_campaign_count(&e).min(start.saturating_add(limit.into()));
+
+        let mut campaigns = Vec::new(&e);
+        for index in start..end {
+            campaigns.push_back(get_campaign(&e, index));
+        }
+        campaigns
+    }
+}
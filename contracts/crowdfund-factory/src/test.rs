@@ -0,0 +1,203 @@
+#![cfg(test)]
+extern crate std;
+
+use crate::{Campaign, CrowdfundFactory, CrowdfundFactoryClient, Fee};
+use soroban_sdk::{
+    testutils::{Address as AddressTestTrait, AuthorizedFunction, AuthorizedInvocation},
+    token, vec, Address, BytesN, Env, IntoVal, Symbol,
+};
+
+mod crowdfund {
+    soroban_sdk::contractimport!(
+        file = "../../target/wasm32-unknown-unknown/release/soroban_crowdfund_contract.wasm"
+    );
+}
+
+fn create_factory_contract<'a>(
+    e: &Env,
+    fee: &Option<Fee>,
+) -> (CrowdfundFactoryClient<'a>, BytesN<32>) {
+    // Every call into a deployed crowdfund instantiates its WASM, which soon
+    // exceeds the default budget
+    e.budget().reset_unlimited();
+    let wasm_hash = e.deployer().upload_contract_wasm(crowdfund::WASM);
+    let factory = CrowdfundFactoryClient::new(e, &e.register_contract(None, CrowdfundFactory));
+    factory
+        .mock_all_auths()
+        .initialize(&Address::random(e), &wasm_hash, fee);
+    (factory, wasm_hash)
+}
+
+#[test]
+fn test_deploy() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let (factory, wasm_hash) = create_factory_contract(&e, &None);
+    let creator = Address::random(&e);
+    let recipient = Address::random(&e);
+    let token = Address::random(&e);
+    let deadline = e.ledger().timestamp() + 10;
+
+    assert_eq!(factory.wasm_hash(), wasm_hash);
+    assert_eq!(factory.nonce(&creator), 0);
+
+    let expected_address = factory.next_address(&creator);
+    let address = factory.deploy(&creator, &recipient, &deadline, &15, &token);
+    // The recipient authorized the campaign being created for them
+    assert!(e.auths().iter().any(|(address, _)| *address == recipient));
+    assert_eq!(address, expected_address);
+    assert_eq!(factory.nonce(&creator), 1);
+
+    // The deployed crowdfund was initialized as campaign 0
+    let crowdfund = crowdfund::Client::new(&e, &address);
+    assert_eq!(crowdfund.recipient(&0), recipient);
+    assert_eq!(
+        crowdfund.deadline(&0),
+        crowdfund::Deadline::Timestamp(deadline)
+    );
+    assert_eq!(crowdfund.target(&0), 15);
+    assert_eq!(crowdfund.token(&0), token);
+    assert_eq!(crowdfund.admin(), factory.address);
+
+    assert_eq!(factory.campaign_count(), 1);
+    assert_eq!(
+        factory.campaign(&0),
+        Campaign {
+            address,
+            creator,
+            token,
+            target: 15,
+            deadline,
+        }
+    );
+}
+
+#[test]
+fn test_deploy_with_fee() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let treasury = Address::random(&e);
+    let fee = Fee {
+        fee_bps: 250,
+        treasury: treasury.clone(),
+    };
+    let (factory, _) = create_factory_contract(&e, &Some(fee.clone()));
+    assert_eq!(factory.fee(), Some(fee));
+
+    // Every deployed crowdfund takes the factory's fee
+    let address = factory.deploy(
+        &Address::random(&e),
+        &Address::random(&e),
+        &10,
+        &15,
+        &Address::random(&e),
+    );
+    let crowdfund = crowdfund::Client::new(&e, &address);
+    assert_eq!(
+        crowdfund.fee_info(&0),
+        Some(crowdfund::Fee {
+            fee_bps: 250,
+            treasury,
+        })
+    );
+}
+
+#[test]
+fn test_sweep_excess() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let (factory, _) = create_factory_contract(&e, &None);
+    let token = token::Client::new(&e, &e.register_stellar_asset_contract(Address::random(&e)));
+    let address = factory.deploy(
+        &Address::random(&e),
+        &Address::random(&e),
+        &10,
+        &15,
+        &token.address,
+    );
+
+    // Tokens sent straight to the crowdfund are recovered through the factory
+    let sender = Address::random(&e);
+    token::StellarAssetClient::new(&e, &token.address).mint(&address, &4);
+    assert_eq!(factory.sweep_excess(&0, &token.address, &sender), 4);
+    assert_eq!(e.auths()[0].0, factory.admin());
+    assert_eq!(token.balance(&sender), 4);
+    assert_eq!(token.balance(&address), 0);
+}
+
+#[test]
+fn test_campaigns_pagination() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let (factory, _) = create_factory_contract(&e, &None);
+    let creator = Address::random(&e);
+    let recipient = Address::random(&e);
+    let token = Address::random(&e);
+
+    let first = factory.deploy(&creator, &recipient, &10, &15, &token);
+    let second = factory.deploy(&creator, &recipient, &20, &25, &token);
+    let third = factory.deploy(&creator, &recipient, &30, &35, &token);
+
+    // The creator's nonce salts each deployment to a distinct address
+    assert_ne!(first, second);
+    assert_ne!(second, third);
+    assert_eq!(factory.campaign_count(), 3);
+
+    let page = factory.campaigns(&0, &2);
+    assert_eq!(page.len(), 2);
+    assert_eq!(page.get_unchecked(0).address, first);
+    assert_eq!(page.get_unchecked(1).address, second);
+
+    let page = factory.campaigns(&2, &2);
+    assert_eq!(page.len(), 1);
+    assert_eq!(page.get_unchecked(0).address, third);
+    assert_eq!(page.get_unchecked(0).deadline, 30);
+
+    assert_eq!(factory.campaigns(&3, &2), vec![&e]);
+}
+
+#[test]
+fn test_admin() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    let wasm_hash = e.deployer().upload_contract_wasm(crowdfund::WASM);
+    let factory = CrowdfundFactoryClient::new(&e, &e.register_contract(None, CrowdfundFactory));
+    let admin = Address::random(&e);
+
+    factory
+        .mock_all_auths()
+        .initialize(&admin, &wasm_hash, &None);
+    assert_eq!(
+        e.auths(),
+        std::vec![(
+            admin.clone(),
+            AuthorizedInvocation {
+                function: AuthorizedFunction::Contract((
+                    factory.address.clone(),
+                    Symbol::new(&e, "initialize"),
+                    (admin.clone(), wasm_hash, None::<Fee>).into_val(&e),
+                )),
+                sub_invocations: std::vec![]
+            }
+        )]
+    );
+    assert_eq!(factory.admin(), admin);
+
+    let new_hash = BytesN::from_array(&e, &[1; 32]);
+    factory.mock_all_auths().set_wasm_hash(&new_hash);
+    assert_eq!(e.auths()[0].0, admin);
+    assert_eq!(factory.wasm_hash(), new_hash);
+}
+
+#[test]
+#[should_panic(expected = "already initialized")]
+fn initialize_already_initialized() {
+    let e = Env::default();
+    let (factory, wasm_hash) = create_factory_contract(&e, &None);
+
+    factory.initialize(&Address::random(&e), &wasm_hash, &None);
+}
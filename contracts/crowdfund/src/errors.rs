@@ -0,0 +1,35 @@
+use soroban_sdk::contracterror;
+
+/// Errors returned by the crowdfund contract. The codes are part of the
+/// contract's interface and must not be renumbered.
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum Error {
+    NotInitialized = 1,
+    AlreadyInitialized = 2,
+    InvalidAmount = 3,
+    SaleNotRunning = 4,
+    SaleStillRunning = 5,
+    RecipientMayNotDeposit = 6,
+    OnlyRecipientMayWithdraw = 7,
+    AlreadyClaimed = 8,
+    RecipientMayNotWithdraw = 9,
+    InsufficientPledge = 10,
+    PledgesLocked = 11,
+    InvalidOptions = 12,
+    HardCapExceeded = 13,
+    BelowMinContribution = 14,
+    AboveMaxPerDonor = 15,
+    TargetNotReached = 16,
+    ExtensionNotAllowed = 17,
+    InvalidDeadline = 18,
+    NoExtensionProposed = 19,
+    ApprovalRequired = 20,
+    NotASigner = 21,
+    AlreadyApproved = 22,
+    NotAKeeper = 23,
+    NoPayoutProposed = 24,
+    InsufficientAllowance = 25,
+    PayoutAlreadyProposed = 26,
+}
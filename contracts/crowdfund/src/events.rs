@@ -1,12 +1,96 @@
-use soroban_sdk::{vec, Env, Symbol};
+use crate::Deadline;
+use soroban_sdk::{vec, Address, Env, String, Symbol, Vec};
 
-pub(crate) fn pledged_amount_changed(e: &Env, total_amount: i128) {
-    let topics = (Symbol::new(e, "pledged_amount_changed"),);
+pub(crate) fn pledged_amount_changed(e: &Env, campaign_id: u64, total_amount: i128) {
+    let topics = (Symbol::new(e, "pledged_amount_changed"), campaign_id);
     e.events().publish(topics, total_amount);
 }
 
-pub(crate) fn target_reached(e: &Env, pledged: i128, target: i128) {
-    let topics = (Symbol::new(e, "target_reached"),);
-    let event_payload = vec![e, pledged, target];
+// Pledges to a campaign reached one of its thresholds
+fn threshold_reached(e: &Env, name: &str, campaign_id: u64, pledged: i128, threshold: i128) {
+    let topics = (Symbol::new(e, name), campaign_id);
+    let event_payload = vec![e, pledged, threshold];
     e.events().publish(topics, event_payload);
 }
+
+pub(crate) fn soft_cap_reached(e: &Env, campaign_id: u64, pledged: i128, soft_cap: i128) {
+    threshold_reached(e, "soft_cap_reached", campaign_id, pledged, soft_cap);
+}
+
+pub(crate) fn target_reached(e: &Env, campaign_id: u64, pledged: i128, target: i128) {
+    threshold_reached(e, "target_reached", campaign_id, pledged, target);
+}
+
+pub(crate) fn excess_swept(e: &Env, token: Address, to: Address, amount: i128) {
+    let topics = (Symbol::new(e, "excess_swept"), token);
+    e.events().publish(topics, (to, amount));
+}
+
+pub(crate) fn campaign_cancelled(e: &Env, campaign_id: u64, reason: String) {
+    let topics = (Symbol::new(e, "campaign_cancelled"), campaign_id);
+    e.events().publish(topics, reason);
+}
+
+pub(crate) fn campaign_closed(e: &Env, campaign_id: u64, total_amount: i128) {
+    let topics = (Symbol::new(e, "campaign_closed"), campaign_id);
+    e.events().publish(topics, total_amount);
+}
+
+pub(crate) fn deadline_extended(e: &Env, campaign_id: u64, deadline: Deadline) {
+    let topics = (Symbol::new(e, "deadline_extended"), campaign_id);
+    e.events().publish(topics, deadline);
+}
+
+pub(crate) fn extension_proposed(e: &Env, campaign_id: u64, deadline: Deadline) {
+    let topics = (Symbol::new(e, "extension_proposed"), campaign_id);
+    e.events().publish(topics, deadline);
+}
+
+pub(crate) fn payout_proposed(e: &Env, campaign_id: u64, signer: Address, destination: Address) {
+    let topics = (Symbol::new(e, "payout_proposed"), campaign_id);
+    e.events().publish(topics, (signer, destination));
+}
+
+pub(crate) fn payout_approved(e: &Env, campaign_id: u64, signer: Address, approvals: u32) {
+    let topics = (Symbol::new(e, "payout_approved"), campaign_id);
+    e.events().publish(topics, (signer, approvals));
+}
+
+pub(crate) fn payout_released(
+    e: &Env,
+    campaign_id: u64,
+    destination: Address,
+    approvers: Vec<Address>,
+) {
+    let topics = (Symbol::new(e, "payout_released"), campaign_id);
+    e.events().publish(topics, (destination, approvers));
+}
+
+pub(crate) fn claimed(
+    e: &Env,
+    campaign_id: u64,
+    owner: Address,
+    destination: Address,
+    amount: i128,
+    fee: i128,
+) {
+    let topics = (Symbol::new(e, "claimed"), campaign_id);
+    e.events()
+        .publish(topics, (owner, destination, amount, fee));
+}
+
+pub(crate) fn refunded(
+    e: &Env,
+    campaign_id: u64,
+    owner: Address,
+    destination: Address,
+    amount: i128,
+) {
+    let topics = (Symbol::new(e, "refunded"), campaign_id);
+    e.events().publish(topics, (owner, destination, amount));
+}
+
+pub(crate) fn payout_address_changed(e: &Env, campaign_id: u64, address: Address) {
+    let topics = (Symbol::new(e, "payout_address_changed"), campaign_id);
+    e.events().publish(topics, address);
+}
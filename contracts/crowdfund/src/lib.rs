@@ -1,35 +1,254 @@
 #![no_std]
 use soroban_sdk::{
-    contract, contractimpl, contractmeta, contracttype, token, Address, Env, IntoVal, Val,
+    contract, contractimpl, contractmeta, contracttype, token, Address, Env, IntoVal, String,
+    TryFromVal, Val, Vec,
 };
 
+pub use crate::errors::Error;
+
+mod errors;
 mod events;
 mod test;
 mod testutils;
 
+pub(crate) const DAY_IN_LEDGERS: u32 = 17280;
+pub(crate) const USER_BUMP_AMOUNT: u32 = 30 * DAY_IN_LEDGERS;
+pub(crate) const USER_LIFETIME_THRESHOLD: u32 = USER_BUMP_AMOUNT - DAY_IN_LEDGERS;
+pub(crate) const CAMPAIGN_BUMP_AMOUNT: u32 = 30 * DAY_IN_LEDGERS;
+pub(crate) const CAMPAIGN_LIFETIME_THRESHOLD: u32 = CAMPAIGN_BUMP_AMOUNT - DAY_IN_LEDGERS;
+
+#[derive(Clone)]
+#[contracttype]
+pub struct UserDataKey {
+    pub campaign_id: u64,
+    pub user: Address,
+}
+
+#[derive(Clone)]
+#[contracttype]
+pub struct PayoutDataKey {
+    pub campaign_id: u64,
+    pub destination: Address,
+}
+
 #[derive(Clone)]
 #[contracttype]
 pub enum DataKey {
-    Deadline,
-    Recipient,
-    Started,
-    Target,
-    Token,
-    User(Address),
-    RecipientClaimed,
+    Admin,
+    CampaignCount,
+    Deadline(u64),
+    Recipient(u64),
+    Started(u64),
+    Target(u64),
+    Token(u64),
+    User(UserDataKey),
+    RecipientClaimed(u64),
+    Cancelled(u64),
+    ClosedEarly(u64),
+    Extended(u64),
+    ExtensionProposal(u64),
+    ExtensionProposals(u64),
+    ExtensionVote(UserDataKey),
+    PledgeApproval(UserDataKey),
+    Fee(u64),
+    PlatformFee,
+    PayoutAddress(u64),
+    PayoutProposal(PayoutDataKey),
+    TotalPledged(u64),
+    Reserved(Address),
+    Options(u64),
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+/// When a campaign ends: at a ledger timestamp, or at a ledger sequence number.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[contracttype]
+pub enum Deadline {
+    Timestamp(u64),
+    Ledger(u32),
+}
+
+/// What happens to the pledges of a campaign that misses its target.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[contracttype]
+#[repr(u32)]
+pub enum FundingMode {
+    /// The donors get their pledges back.
+    #[default]
+    AllOrNothing = 0,
+    /// The recipient receives whatever was pledged.
+    KeepItAll = 1,
+}
+
+/// Optional settings of a campaign, fixed when it is created.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct CampaignOptions {
+    /// Donors may not unpledge once the target has been reached.
+    pub unpledge_locked_at_target: bool,
+    /// Donors may not unpledge within this many seconds of the deadline, or
+    /// ledgers if the deadline is a ledger sequence number.
+    pub unpledge_lock_window: u64,
+    /// The most the campaign accepts, or 0 for no limit. The campaign
+    /// succeeds as soon as this is reached.
+    pub hard_cap: i128,
+    /// Accept the part of a deposit that fits under the hard cap, rather than
+    /// rejecting the whole deposit.
+    pub accept_partial_deposits: bool,
+    /// The least each donor's pledge may add up to.
+    pub min_contribution: i128,
+    /// The most each donor's pledge may add up to, or 0 for no limit.
+    pub max_per_donor: i128,
+    pub funding_mode: FundingMode,
+    /// The least the campaign needs to succeed at its deadline, or 0 to use
+    /// the target. Must not exceed the target.
+    pub soft_cap: i128,
+    /// When the campaign starts taking deposits, or 0 to start straight away.
+    /// Must be before the deadline if that is a timestamp too.
+    pub start: u64,
+    /// Deposits within this many seconds of the deadline, or ledgers if the
+    /// deadline is a ledger sequence number, push it out by
+    /// `extension_duration`. 0 turns extensions off.
+    pub extension_window: u64,
+    pub extension_duration: u64,
+    /// The most the deadline may be pushed out by in total.
+    pub max_extension: u64,
+    /// The share of the total pledged, in basis points, that donors approving
+    /// a proposed extension must exceed for the deadline to change. 0 means
+    /// the recipient may not propose extensions.
+    pub extension_approval_bps: u32,
+    /// How the payout is split between the recipient's team, in shares that
+    /// add up to 10000 basis points. Empty pays it all to the recipient.
+    pub payout: Vec<Share>,
+    /// The team members who must approve the payout with `approve_payout`
+    /// before it is released. Empty lets it be withdrawn straight away.
+    pub signers: Vec<Address>,
+    /// How many of the signers must approve the payout.
+    pub threshold: u32,
+    /// Addresses that may withdraw on behalf of donors and recipients with
+    /// `keeper_withdraw`.
+    pub keepers: Vec<Address>,
+}
+
+impl CampaignOptions {
+    /// The options of a campaign that opts in to none of them.
+    #[must_use]
+    pub fn new(e: &Env) -> Self {
+        Self {
+            unpledge_locked_at_target: false,
+            unpledge_lock_window: 0,
+            hard_cap: 0,
+            accept_partial_deposits: false,
+            min_contribution: 0,
+            max_per_donor: 0,
+            funding_mode: FundingMode::default(),
+            soft_cap: 0,
+            start: 0,
+            extension_window: 0,
+            extension_duration: 0,
+            max_extension: 0,
+            extension_approval_bps: 0,
+            payout: Vec::new(e),
+            signers: Vec::new(e),
+            threshold: 0,
+            keepers: Vec::new(e),
+        }
+    }
+}
+
+/// A payee's part of a campaign's payout, in basis points.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct Share {
+    pub address: Address,
+    pub bps: u32,
+}
+
+/// The platform's cut of a successful campaign: `fee_bps` basis points of the
+/// amount raised go to `treasury` when the recipient claims.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct Fee {
+    pub fee_bps: u32,
+    pub treasury: Address,
+}
+
+/// A later deadline proposed by the recipient, and the sum of the pledges of
+/// the donors approving it. Proposals of a campaign are numbered from 0.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct ExtensionProposal {
+    pub id: u32,
+    pub deadline: Deadline,
+    pub approved: i128,
+}
+
+/// A donor's approval of an extension proposal, and the pledge it counts.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct ExtensionVote {
+    pub proposal: u32,
+    pub pledge: i128,
+}
+
+/// A payout proposed by one of the signers, and the signers approving it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct PayoutProposal {
+    pub destination: Address,
+    pub approvers: Vec<Address>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[contracttype]
 #[repr(u32)]
 pub enum State {
     Running = 0,
     Success = 1,
     Expired = 2,
+    Claimed = 3,
+    Cancelled = 4,
+    NotStarted = 5,
 }
 
-impl IntoVal<Env, Val> for State {
-    fn into_val(&self, env: &Env) -> Val {
-        (*self as u32).into_val(env)
+#[derive(Clone, Copy)]
+enum Action {
+    Deposit,
+    Claim,
+    Refund,
+    Cancel,
+    Unpledge,
+    CloseEarly,
+    Extend,
+}
+
+// The transition table of the campaign state machine: the actions each state
+// allows, and the error every other action is rejected with.
+fn check_transition(state: State, action: Action) -> Result<(), Error> {
+    match (state, action) {
+        (
+            State::Running,
+            Action::Deposit
+            | Action::Cancel
+            | Action::Unpledge
+            | Action::CloseEarly
+            | Action::Extend,
+        )
+        | (State::Success, Action::Claim)
+        | (State::Expired | State::Cancelled, Action::Refund) => Ok(()),
+        (
+            _,
+            Action::Deposit
+            | Action::Cancel
+            | Action::Unpledge
+            | Action::CloseEarly
+            | Action::Extend,
+        ) => Err(Error::SaleNotRunning),
+        (State::NotStarted | State::Running, Action::Claim | Action::Refund) => {
+            Err(Error::SaleStillRunning)
+        }
+        (State::Claimed, Action::Claim) => Err(Error::AlreadyClaimed),
+        (State::Success | State::Claimed, Action::Refund) => Err(Error::OnlyRecipientMayWithdraw),
+        (State::Expired | State::Cancelled, Action::Claim) => Err(Error::RecipientMayNotWithdraw),
     }
 }
 
@@ -37,103 +256,660 @@ fn get_ledger_timestamp(e: &Env) -> u64 {
     e.ledger().timestamp()
 }
 
-fn get_recipient(e: &Env) -> Address {
-    e.storage()
-        .instance()
-        .get::<_, Address>(&DataKey::Recipient)
-        .expect("not initialized")
+// Campaigns, and the pledges reserved per token, are kept in persistent
+// storage under keys of their own. The instance entry is loaded on every call,
+// so it only holds what is shared by the whole contract.
+fn get_persistent<V: TryFromVal<Env, Val>>(e: &Env, key: &DataKey) -> Option<V> {
+    let value = e.storage().persistent().get::<_, V>(key);
+    if value.is_some() {
+        e.storage()
+            .persistent()
+            .bump(key, CAMPAIGN_LIFETIME_THRESHOLD, CAMPAIGN_BUMP_AMOUNT);
+    }
+    value
 }
 
-fn get_recipient_claimed(e: &Env) -> bool {
+fn set_persistent<V: IntoVal<Env, Val>>(e: &Env, key: &DataKey, value: &V) {
+    e.storage().persistent().set(key, value);
     e.storage()
-        .instance()
-        .get::<_, bool>(&DataKey::RecipientClaimed)
-        .expect("not initialized")
+        .persistent()
+        .bump(key, CAMPAIGN_LIFETIME_THRESHOLD, CAMPAIGN_BUMP_AMOUNT);
 }
 
-fn get_deadline(e: &Env) -> u64 {
-    e.storage()
-        .instance()
-        .get::<_, u64>(&DataKey::Deadline)
-        .expect("not initialized")
+fn remove_persistent(e: &Env, key: &DataKey) {
+    e.storage().persistent().remove(key);
 }
 
-fn get_started(e: &Env) -> u64 {
+fn get_campaign_count(e: &Env) -> u64 {
     e.storage()
         .instance()
-        .get::<_, u64>(&DataKey::Started)
-        .expect("not initialized")
+        .get::<_, u64>(&DataKey::CampaignCount)
+        .unwrap_or(0)
 }
 
-fn get_target_amount(e: &Env) -> i128 {
-    e.storage()
-        .instance()
-        .get::<_, i128>(&DataKey::Target)
-        .expect("not initialized")
+fn get_recipient(e: &Env, campaign_id: u64) -> Result<Address, Error> {
+    get_persistent(e, &DataKey::Recipient(campaign_id)).ok_or(Error::NotInitialized)
 }
 
-fn get_token(e: &Env) -> Address {
-    e.storage()
-        .instance()
-        .get::<_, Address>(&DataKey::Token)
-        .expect("not initialized")
+fn get_recipient_claimed(e: &Env, campaign_id: u64) -> Result<bool, Error> {
+    get_persistent(e, &DataKey::RecipientClaimed(campaign_id)).ok_or(Error::NotInitialized)
+}
+
+fn get_cancelled(e: &Env, campaign_id: u64) -> Result<bool, Error> {
+    get_persistent(e, &DataKey::Cancelled(campaign_id)).ok_or(Error::NotInitialized)
+}
+
+fn get_closed_early(e: &Env, campaign_id: u64) -> Result<bool, Error> {
+    get_persistent(e, &DataKey::ClosedEarly(campaign_id)).ok_or(Error::NotInitialized)
+}
+
+// How far the deadline has been pushed out by deposits close to it
+fn get_extended(e: &Env, campaign_id: u64) -> Result<u64, Error> {
+    get_persistent::<u64>(e, &DataKey::Extended(campaign_id)).ok_or(Error::NotInitialized)
+}
+
+fn get_extension_proposal(e: &Env, campaign_id: u64) -> Option<ExtensionProposal> {
+    get_persistent(e, &DataKey::ExtensionProposal(campaign_id))
+}
+
+fn get_fee(e: &Env, campaign_id: u64) -> Option<Fee> {
+    get_persistent(e, &DataKey::Fee(campaign_id))
+}
+
+fn get_platform_fee(e: &Env) -> Option<Fee> {
+    e.storage().instance().get::<_, Fee>(&DataKey::PlatformFee)
+}
+
+fn set_platform_fee(e: &Env, fee: Option<Fee>) -> Result<(), Error> {
+    match fee {
+        Some(fee) if fee.fee_bps > 10_000 => return Err(Error::InvalidOptions),
+        Some(fee) => e.storage().instance().set(&DataKey::PlatformFee, &fee),
+        None => e.storage().instance().remove(&DataKey::PlatformFee),
+    }
+    Ok(())
+}
+
+// Payouts to different destinations are proposed and approved independently,
+// so no signer can discard the approvals others have given
+fn get_payout_proposal(e: &Env, campaign_id: u64, destination: &Address) -> Option<PayoutProposal> {
+    let key = PayoutDataKey {
+        campaign_id,
+        destination: destination.clone(),
+    };
+    get_persistent(e, &DataKey::PayoutProposal(key))
+}
+
+// Where the recipient's payout goes, which is the recipient itself unless it
+// set another address
+fn get_payout_address(e: &Env, campaign_id: u64) -> Result<Address, Error> {
+    match get_persistent(e, &DataKey::PayoutAddress(campaign_id)) {
+        Some(address) => Ok(address),
+        None => get_recipient(e, campaign_id),
+    }
+}
+
+fn get_deadline(e: &Env, campaign_id: u64) -> Result<Deadline, Error> {
+    get_persistent(e, &DataKey::Deadline(campaign_id)).ok_or(Error::NotInitialized)
+}
+
+fn get_started(e: &Env, campaign_id: u64) -> Result<u64, Error> {
+    get_persistent::<u64>(e, &DataKey::Started(campaign_id)).ok_or(Error::NotInitialized)
+}
+
+fn get_target_amount(e: &Env, campaign_id: u64) -> Result<i128, Error> {
+    get_persistent::<i128>(e, &DataKey::Target(campaign_id)).ok_or(Error::NotInitialized)
+}
+
+fn get_token(e: &Env, campaign_id: u64) -> Result<Address, Error> {
+    get_persistent(e, &DataKey::Token(campaign_id)).ok_or(Error::NotInitialized)
+}
+
+fn get_options(e: &Env, campaign_id: u64) -> Result<CampaignOptions, Error> {
+    get_persistent(e, &DataKey::Options(campaign_id)).ok_or(Error::NotInitialized)
+}
+
+fn get_user_deposited(e: &Env, campaign_id: u64, user: &Address) -> i128 {
+    let key = DataKey::User(UserDataKey {
+        campaign_id,
+        user: user.clone(),
+    });
+    if let Some(amount) = e.storage().persistent().get::<_, i128>(&key) {
+        e.storage()
+            .persistent()
+            .bump(&key, USER_LIFETIME_THRESHOLD, USER_BUMP_AMOUNT);
+        amount
+    } else {
+        0
+    }
 }
 
-fn get_user_deposited(e: &Env, user: &Address) -> i128 {
+// The amount pledged to a campaign. Several campaigns may share a token, so
+// the contract's token balance cannot be used to tell them apart.
+fn get_total_pledged(e: &Env, campaign_id: u64) -> Result<i128, Error> {
+    get_persistent::<i128>(e, &DataKey::TotalPledged(campaign_id)).ok_or(Error::NotInitialized)
+}
+
+// The amount of the campaign's tokens still held by the contract
+fn get_balance(e: &Env, campaign_id: u64) -> Result<i128, Error> {
+    if get_recipient_claimed(e, campaign_id)? {
+        return Ok(0);
+    }
+    get_total_pledged(e, campaign_id)
+}
+
+// The amount of `token` held by the contract on behalf of campaigns. Anything
+// beyond it was transferred to the contract directly.
+fn get_reserved(e: &Env, token_id: &Address) -> i128 {
+    get_persistent::<i128>(e, &DataKey::Reserved(token_id.clone())).unwrap_or(0)
+}
+
+fn get_admin(e: &Env) -> Result<Address, Error> {
     e.storage()
         .instance()
-        .get::<_, i128>(&DataKey::User(user.clone()))
-        .unwrap_or(0)
+        .get::<_, Address>(&DataKey::Admin)
+        .ok_or(Error::NotInitialized)
+}
+
+fn get_excess(e: &Env, token_id: &Address) -> i128 {
+    let client = token::Client::new(e, token_id);
+    let token_balance = client.balance(&e.current_contract_address());
+    token_balance - get_reserved(e, token_id)
 }
 
-fn get_balance(e: &Env, contract_id: &Address) -> i128 {
-    let client = token::Client::new(e, contract_id);
-    client.balance(&e.current_contract_address())
+// The least a campaign needs to succeed at its deadline
+fn get_soft_cap(e: &Env, campaign_id: u64) -> Result<i128, Error> {
+    let soft_cap = get_options(e, campaign_id)?.soft_cap;
+    if soft_cap == 0 {
+        return get_target_amount(e, campaign_id);
+    }
+    Ok(soft_cap)
 }
 
-fn target_reached(e: &Env, token_id: &Address) -> bool {
-    let target_amount = get_target_amount(e);
-    let token_balance = get_balance(e, token_id);
+fn threshold_reached(e: &Env, campaign_id: u64, threshold: i128) -> Result<bool, Error> {
+    let total_pledged = get_total_pledged(e, campaign_id)?;
 
-    if token_balance >= target_amount {
-        return true;
+    if total_pledged >= threshold {
+        return Ok(true);
     };
-    false
+    Ok(false)
+}
+
+fn target_reached(e: &Env, campaign_id: u64) -> Result<bool, Error> {
+    threshold_reached(e, campaign_id, get_target_amount(e, campaign_id)?)
+}
+
+fn soft_cap_reached(e: &Env, campaign_id: u64) -> Result<bool, Error> {
+    threshold_reached(e, campaign_id, get_soft_cap(e, campaign_id)?)
+}
+
+// Whether `user` is the recipient or one of the payees of a campaign
+fn is_recipient(e: &Env, campaign_id: u64, user: &Address) -> Result<bool, Error> {
+    if *user == get_recipient(e, campaign_id)? {
+        return Ok(true);
+    }
+    let payout = get_options(e, campaign_id)?.payout;
+    Ok(payout.iter().any(|share| share.address == *user))
+}
+
+fn hard_cap_reached(e: &Env, campaign_id: u64) -> Result<bool, Error> {
+    let hard_cap = get_options(e, campaign_id)?.hard_cap;
+    Ok(hard_cap > 0 && get_total_pledged(e, campaign_id)? >= hard_cap)
+}
+
+// How long until the deadline, in seconds or ledgers depending on its kind
+fn get_time_left(e: &Env, deadline: Deadline) -> u64 {
+    match deadline {
+        Deadline::Timestamp(timestamp) => timestamp.saturating_sub(get_ledger_timestamp(e)),
+        Deadline::Ledger(sequence) => u64::from(sequence.saturating_sub(e.ledger().sequence())),
+    }
 }
 
-fn get_state(e: &Env) -> State {
-    let deadline = get_deadline(e);
-    let token_id = get_token(e);
+// The deadline pushed out by `by` seconds or ledgers
+fn extend(deadline: Deadline, by: u64) -> Deadline {
+    match deadline {
+        Deadline::Timestamp(timestamp) => Deadline::Timestamp(timestamp.saturating_add(by)),
+        Deadline::Ledger(sequence) => {
+            Deadline::Ledger(sequence.saturating_add(u32::try_from(by).unwrap_or(u32::MAX)))
+        }
+    }
+}
+
+// Push the deadline out if a deposit comes in close to it, as far as the
+// campaign's total extension allows
+fn extend_near_deadline(e: &Env, campaign_id: u64) -> Result<(), Error> {
+    let options = get_options(e, campaign_id)?;
+    let deadline = get_deadline(e, campaign_id)?;
+    if options.extension_window == 0 || get_time_left(e, deadline) > options.extension_window {
+        return Ok(());
+    }
+
+    let extended = get_extended(e, campaign_id)?;
+    let extension = options
+        .extension_duration
+        .min(options.max_extension.saturating_sub(extended));
+    if extension == 0 {
+        return Ok(());
+    }
+    let deadline = extend(deadline, extension);
+    set_deadline(e, campaign_id, &deadline);
+    set_extended(e, campaign_id, extended + extension);
+    events::deadline_extended(e, campaign_id, deadline);
+    Ok(())
+}
+
+fn is_later(deadline: Deadline, than: Deadline) -> bool {
+    match (deadline, than) {
+        (Deadline::Timestamp(deadline), Deadline::Timestamp(than)) => deadline > than,
+        (Deadline::Ledger(deadline), Deadline::Ledger(than)) => deadline > than,
+        _ => false,
+    }
+}
+
+// Whether the pledges of the approvers of a proposed extension add up to more
+// than the campaign's approval share of the total pledged
+fn extension_approved(
+    e: &Env,
+    campaign_id: u64,
+    proposal: &ExtensionProposal,
+) -> Result<bool, Error> {
+    let approval_bps = i128::from(get_options(e, campaign_id)?.extension_approval_bps);
+    Ok(proposal.approved * 10_000 > get_total_pledged(e, campaign_id)? * approval_bps)
+}
+
+// The pledge `user`'s approval adds to the open proposal, if they approve it.
+// Approvals of earlier proposals are left behind under the same key.
+fn get_extension_vote(e: &Env, campaign_id: u64, user: &Address, proposal: u32) -> i128 {
+    let key = DataKey::ExtensionVote(UserDataKey {
+        campaign_id,
+        user: user.clone(),
+    });
+    match get_persistent::<ExtensionVote>(e, &key) {
+        Some(vote) if vote.proposal == proposal => vote.pledge,
+        _ => 0,
+    }
+}
+
+fn set_extension_vote(e: &Env, campaign_id: u64, user: &Address, vote: Option<ExtensionVote>) {
+    let key = DataKey::ExtensionVote(UserDataKey {
+        campaign_id,
+        user: user.clone(),
+    });
+    match vote {
+        Some(vote) => set_persistent(e, &key, &vote),
+        None => remove_persistent(e, &key),
+    }
+}
+
+// Keep the open proposal's approvals in step with a change to the pledge of
+// `user`, should they approve it
+fn update_extension_vote(e: &Env, campaign_id: u64, user: &Address, pledge: i128) {
+    let Some(mut proposal) = get_extension_proposal(e, campaign_id) else {
+        return;
+    };
+    let counted = get_extension_vote(e, campaign_id, user, proposal.id);
+    if counted == 0 {
+        return;
+    }
+    proposal.approved += pledge - counted;
+    set_extension_proposal(e, campaign_id, &proposal);
+    // A donor who unpledged everything no longer approves
+    let vote = if pledge > 0 {
+        Some(ExtensionVote {
+            proposal: proposal.id,
+            pledge,
+        })
+    } else {
+        None
+    };
+    set_extension_vote(e, campaign_id, user, vote);
+}
+
+fn unpledge_locked(e: &Env, campaign_id: u64) -> Result<bool, Error> {
+    let options = get_options(e, campaign_id)?;
+    if options.unpledge_locked_at_target && target_reached(e, campaign_id)? {
+        return Ok(true);
+    }
+    let deadline = get_deadline(e, campaign_id)?;
+    Ok(get_time_left(e, deadline) <= options.unpledge_lock_window)
+}
+
+fn get_state(e: &Env, campaign_id: u64) -> Result<State, Error> {
+    let started = get_started(e, campaign_id)?;
+    let deadline = get_deadline(e, campaign_id)?;
     let current_timestamp = get_ledger_timestamp(e);
 
-    if current_timestamp < deadline {
-        return State::Running;
+    if get_recipient_claimed(e, campaign_id)? {
+        return Ok(State::Claimed);
     };
-    if get_recipient_claimed(e) || target_reached(e, &token_id) {
-        return State::Success;
+    if get_cancelled(e, campaign_id)? {
+        return Ok(State::Cancelled);
     };
-    State::Expired
+    if current_timestamp < started {
+        return Ok(State::NotStarted);
+    };
+    if hard_cap_reached(e, campaign_id)? || get_closed_early(e, campaign_id)? {
+        return Ok(State::Success);
+    };
+    if get_time_left(e, deadline) > 0 {
+        return Ok(State::Running);
+    };
+    if soft_cap_reached(e, campaign_id)?
+        || get_options(e, campaign_id)?.funding_mode == FundingMode::KeepItAll
+    {
+        return Ok(State::Success);
+    };
+    Ok(State::Expired)
+}
+
+fn set_campaign_count(e: &Env, count: u64) {
+    e.storage().instance().set(&DataKey::CampaignCount, &count);
 }
 
-fn set_user_deposited(e: &Env, user: &Address, amount: &i128) {
+fn set_user_deposited(e: &Env, campaign_id: u64, user: &Address, amount: &i128) {
+    let key = DataKey::User(UserDataKey {
+        campaign_id,
+        user: user.clone(),
+    });
+    e.storage().persistent().set(&key, amount);
     e.storage()
-        .instance()
-        .set(&DataKey::User(user.clone()), amount);
+        .persistent()
+        .bump(&key, USER_LIFETIME_THRESHOLD, USER_BUMP_AMOUNT);
+
+    update_extension_vote(e, campaign_id, user, *amount);
 }
 
-fn set_recipient_claimed(e: &Env) {
+// How much `user` has approved pledging to a campaign out of their allowance
+fn get_pledge_approval(e: &Env, campaign_id: u64, user: &Address) -> i128 {
+    let key = DataKey::PledgeApproval(UserDataKey {
+        campaign_id,
+        user: user.clone(),
+    });
+    if let Some(amount) = e.storage().persistent().get::<_, i128>(&key) {
+        e.storage()
+            .persistent()
+            .bump(&key, USER_LIFETIME_THRESHOLD, USER_BUMP_AMOUNT);
+        amount
+    } else {
+        0
+    }
+}
+
+fn set_pledge_approval(e: &Env, campaign_id: u64, user: &Address, amount: i128) {
+    let key = DataKey::PledgeApproval(UserDataKey {
+        campaign_id,
+        user: user.clone(),
+    });
+    if amount == 0 {
+        e.storage().persistent().remove(&key);
+        return;
+    }
+    e.storage().persistent().set(&key, &amount);
     e.storage()
-        .instance()
-        .set(&DataKey::RecipientClaimed, &true);
+        .persistent()
+        .bump(&key, USER_LIFETIME_THRESHOLD, USER_BUMP_AMOUNT);
+}
+
+fn set_total_pledged(e: &Env, campaign_id: u64, amount: &i128) {
+    set_persistent(e, &DataKey::TotalPledged(campaign_id), amount);
+}
+
+fn add_reserved(e: &Env, token_id: &Address, amount: i128) {
+    let reserved = get_reserved(e, token_id) + amount;
+    set_persistent(e, &DataKey::Reserved(token_id.clone()), &reserved);
+}
+
+fn set_recipient_claimed(e: &Env, campaign_id: u64) {
+    set_persistent(e, &DataKey::RecipientClaimed(campaign_id), &true);
+}
+
+fn set_cancelled(e: &Env, campaign_id: u64) {
+    set_persistent(e, &DataKey::Cancelled(campaign_id), &true);
+}
+
+fn set_deadline(e: &Env, campaign_id: u64, deadline: &Deadline) {
+    set_persistent(e, &DataKey::Deadline(campaign_id), deadline);
+}
+
+fn set_extension_proposal(e: &Env, campaign_id: u64, proposal: &ExtensionProposal) {
+    set_persistent(e, &DataKey::ExtensionProposal(campaign_id), proposal);
+}
+
+// The id of the next extension proposal of a campaign
+fn next_extension_proposal(e: &Env, campaign_id: u64) -> u32 {
+    let key = DataKey::ExtensionProposals(campaign_id);
+    let id = get_persistent::<u32>(e, &key).unwrap_or(0);
+    set_persistent(e, &key, &(id + 1));
+    id
+}
+
+fn remove_extension_proposal(e: &Env, campaign_id: u64) {
+    remove_persistent(e, &DataKey::ExtensionProposal(campaign_id));
+}
+
+fn set_extended(e: &Env, campaign_id: u64, extended: u64) {
+    set_persistent(e, &DataKey::Extended(campaign_id), &extended);
+}
+
+fn set_payout_proposal(e: &Env, campaign_id: u64, proposal: &PayoutProposal) {
+    let key = PayoutDataKey {
+        campaign_id,
+        destination: proposal.destination.clone(),
+    };
+    set_persistent(e, &DataKey::PayoutProposal(key), proposal);
+}
+
+fn remove_payout_proposal(e: &Env, campaign_id: u64, destination: &Address) {
+    let key = PayoutDataKey {
+        campaign_id,
+        destination: destination.clone(),
+    };
+    remove_persistent(e, &DataKey::PayoutProposal(key));
+}
+
+fn set_closed_early(e: &Env, campaign_id: u64) {
+    set_persistent(e, &DataKey::ClosedEarly(campaign_id), &true);
 }
 
 // Transfer tokens from the contract to the recipient
-fn transfer(e: &Env, to: &Address, amount: &i128) {
-    let token_contract_id = &get_token(e);
-    let client = token::Client::new(e, token_contract_id);
+fn transfer(e: &Env, token_id: &Address, to: &Address, amount: &i128) {
+    let client = token::Client::new(e, token_id);
     client.transfer(&e.current_contract_address(), to, amount);
 }
 
+// Where `owner`'s payout or refund goes by default: the payout address for
+// the recipient, and the owner itself for anyone else
+fn get_destination(e: &Env, campaign_id: u64, owner: &Address) -> Result<Address, Error> {
+    if *owner == get_recipient(e, campaign_id)? {
+        return get_payout_address(e, campaign_id);
+    }
+    Ok(owner.clone())
+}
+
+// Pay `amount` out to the campaign's payees in proportion to their shares, or
+// to the recipient if it has none. Rounding leftovers go to the last payee.
+// `owner`'s part goes to `destination`, everyone else's to their default
+// destination.
+fn pay_out(
+    e: &Env,
+    campaign_id: u64,
+    token_id: &Address,
+    amount: i128,
+    owner: &Address,
+    destination: &Address,
+) -> Result<(), Error> {
+    let pay = |payee: &Address, amount: i128| -> Result<(), Error> {
+        if payee == owner {
+            transfer(e, token_id, destination, &amount);
+        } else {
+            transfer(
+                e,
+                token_id,
+                &get_destination(e, campaign_id, payee)?,
+                &amount,
+            );
+        }
+        Ok(())
+    };
+
+    let payout = get_options(e, campaign_id)?.payout;
+    let Some(last) = payout.last() else {
+        return pay(&get_recipient(e, campaign_id)?, amount);
+    };
+
+    let mut paid = 0;
+    for share in payout.slice(..payout.len() - 1).iter() {
+        let share_amount = amount * i128::from(share.bps) / 10_000;
+        pay(&share.address, share_amount)?;
+        paid += share_amount;
+    }
+    pay(&last.address, amount - paid)
+}
+
+// Claim the payout for `owner` if it is one of the campaign's recipients, or
+// refund its pledge otherwise, sending its part to `destination`
+fn withdraw_for(
+    e: &Env,
+    campaign_id: u64,
+    owner: &Address,
+    destination: &Address,
+) -> Result<(), Error> {
+    let state = get_state(e, campaign_id)?;
+    let token_id = get_token(e, campaign_id)?;
+
+    if is_recipient(e, campaign_id, owner)? {
+        check_transition(state, Action::Claim)?;
+        if !get_options(e, campaign_id)?.signers.is_empty() {
+            return Err(Error::ApprovalRequired);
+        }
+        claim(e, campaign_id, owner, destination)?;
+    } else {
+        check_transition(state, Action::Refund)?;
+
+        // Withdraw full amount
+        let balance = get_user_deposited(e, campaign_id, owner);
+        set_user_deposited(e, campaign_id, owner, &0);
+        transfer(e, &token_id, destination, &balance);
+
+        let total_pledged = get_total_pledged(e, campaign_id)? - balance;
+        set_total_pledged(e, campaign_id, &total_pledged);
+        add_reserved(e, &token_id, -balance);
+
+        // emit events
+        events::pledged_amount_changed(e, campaign_id, total_pledged);
+        events::refunded(e, campaign_id, owner.clone(), destination.clone(), balance);
+    }
+    Ok(())
+}
+
+// Release the payout of a successful campaign, less the platform fee, on
+// behalf of `owner`, whose part goes to `destination`
+fn claim(e: &Env, campaign_id: u64, owner: &Address, destination: &Address) -> Result<(), Error> {
+    let token_id = get_token(e, campaign_id)?;
+    let amount = get_balance(e, campaign_id)?;
+    let fee = match get_fee(e, campaign_id) {
+        Some(fee) => {
+            let fee_amount = amount * i128::from(fee.fee_bps) / 10_000;
+            transfer(e, &token_id, &fee.treasury, &fee_amount);
+            fee_amount
+        }
+        None => 0,
+    };
+    pay_out(e, campaign_id, &token_id, amount - fee, owner, destination)?;
+    add_reserved(e, &token_id, -amount);
+    set_recipient_claimed(e, campaign_id);
+
+    // emit events
+    events::claimed(
+        e,
+        campaign_id,
+        owner.clone(),
+        destination.clone(),
+        amount - fee,
+        fee,
+    );
+    Ok(())
+}
+
+// Where the tokens of a pledge come from: a transfer the payer authorized, or
+// an allowance the payer granted the contract
+#[derive(Clone, Copy)]
+enum Payer<'a> {
+    Transfer(&'a Address),
+    Allowance(&'a Address),
+}
+
+// Pledge `amount` from `payer` to the campaign, crediting it to `beneficiary`,
+// and return how much of it was accepted
+fn pledge(
+    e: &Env,
+    campaign_id: u64,
+    payer: Payer,
+    beneficiary: &Address,
+    amount: i128,
+) -> Result<i128, Error> {
+    if amount <= 0 {
+        return Err(Error::InvalidAmount);
+    }
+    check_transition(get_state(e, campaign_id)?, Action::Deposit)?;
+    let token_id = get_token(e, campaign_id)?;
+    let current_soft_cap_met = soft_cap_reached(e, campaign_id)?;
+    let current_target_met = target_reached(e, campaign_id)?;
+
+    if is_recipient(e, campaign_id, beneficiary)? {
+        return Err(Error::RecipientMayNotDeposit);
+    }
+
+    let mut amount = amount;
+    let options = get_options(e, campaign_id)?;
+    if options.hard_cap > 0 {
+        let remaining = options.hard_cap - get_total_pledged(e, campaign_id)?;
+        if amount > remaining {
+            if !options.accept_partial_deposits {
+                return Err(Error::HardCapExceeded);
+            }
+            amount = remaining;
+        }
+    }
+
+    let balance = get_user_deposited(e, campaign_id, beneficiary) + amount;
+    if balance < options.min_contribution {
+        return Err(Error::BelowMinContribution);
+    }
+    if options.max_per_donor > 0 && balance > options.max_per_donor {
+        return Err(Error::AboveMaxPerDonor);
+    }
+    set_user_deposited(e, campaign_id, beneficiary, &balance);
+
+    let total_pledged = get_total_pledged(e, campaign_id)? + amount;
+    set_total_pledged(e, campaign_id, &total_pledged);
+    add_reserved(e, &token_id, amount);
+
+    let client = token::Client::new(e, &token_id);
+    let contract = e.current_contract_address();
+    match payer {
+        Payer::Transfer(from) => client.transfer(from, &contract, &amount),
+        Payer::Allowance(from) => client.transfer_from(&contract, from, &contract, &amount),
+    }
+
+    // emit events
+    events::pledged_amount_changed(e, campaign_id, total_pledged);
+    // A campaign without a soft cap of its own is told by target_reached
+    if options.soft_cap != 0 && !current_soft_cap_met && soft_cap_reached(e, campaign_id)? {
+        events::soft_cap_reached(e, campaign_id, total_pledged, get_soft_cap(e, campaign_id)?);
+    }
+    if !current_target_met && target_reached(e, campaign_id)? {
+        // only emit the target reached event once on the pledge that triggers target to be met
+        events::target_reached(
+            e,
+            campaign_id,
+            total_pledged,
+            get_target_amount(e, campaign_id)?,
+        );
+    }
+    extend_near_deadline(e, campaign_id)?;
+    Ok(amount)
+}
+
 // Metadata that is added on to the WASM custom section
 contractmeta!(
     key = "Description",
@@ -146,138 +922,597 @@ struct Crowdfund;
 /*
 How to use this contract to run a crowdfund
 
-1. Call initialize(recipient, deadline_unix_epoch, target_amount, token).
-2. Donors send tokens to this contract's address
-3. Once the target_amount is reached, the contract recipient can withdraw the tokens.
-4. If the deadline passes without reaching the target_amount, the donors can withdraw their tokens again.
+1. The recipient calls create_campaign(recipient, deadline, target_amount, token, options) to get
+   a campaign id, with the deadline given as a unix epoch timestamp or a ledger sequence number, or
+   initialize(recipient, deadline_unix_epoch, target_amount, token, admin, fee) once to set up
+   campaign 0 with the default options. A campaign given a future start
+   takes no deposits until then.
+2. Donors deposit tokens into the campaign, and may unpledge them again while it is running.
+   deposit_for(campaign_id, payer, beneficiary, amount) pledges on someone else's behalf, and
+   deposit_from_allowance(campaign_id, user, amount) out of an allowance granted to the contract,
+   up to what the donor set aside for the campaign with approve_pledge(campaign_id, user, amount).
+3. Once the soft cap (the target_amount unless set lower) is reached, the campaign recipient can
+   withdraw the tokens after the deadline, or straight away if the campaign has a hard cap and it
+   is reached.
+4. If the deadline passes without reaching the soft cap, the donors can withdraw their tokens again,
+   unless the campaign's funding mode is KeepItAll, in which case the recipient withdraws them.
+5. The recipient may cancel(campaign_id, reason) a running campaign, after which the donors can
+   withdraw their tokens again straight away.
+6. The recipient may close_early(campaign_id) a running campaign that has reached its target, and
+   withdraw the tokens without waiting for the deadline.
+7. Campaigns may opt in to having the deadline pushed out by deposits that come in close to it,
+   or to letting the recipient propose_extension(campaign_id, deadline) for the donors to
+   vote_extension(campaign_id, user, approve) on.
+8. While the admin has set_fee(fee), campaigns created send that share of the tokens to the
+   platform's treasury when the recipient withdraws them.
+9. A campaign may split its payout between several payees, any of whom may withdraw it for all.
+10. A campaign with signers releases its payout, rather than through withdraw, once one of them
+    propose_payout(campaign_id, signer, destination) and enough of them approve_payout(campaign_id,
+    signer, destination).
+11. Withdrawals must be authorized by the donor or recipient they pay, unless one of the campaign's
+    keepers makes them with keeper_withdraw(campaign_id, keeper, to).
+12. Donors and recipients may withdraw_to(campaign_id, owner, destination) another address, and
+    the recipient of a campaign without signers may set_payout_address(campaign_id, address) for
+    its payouts.
+13. The admin named in initialize may sweep_excess(token, to) tokens sent to the contract without
+    being pledged, and hand over to another admin with set_admin.
 */
 #[contractimpl]
 #[allow(clippy::needless_pass_by_value)]
 impl Crowdfund {
+    /// Set up the single-campaign deployment, with `admin` as the contract
+    /// admin and `fee` as the platform fee. This is campaign 0, and may only be
+    /// called before any other campaign has been created. The deadline is a
+    /// ledger timestamp.
     pub fn initialize(
         e: Env,
         recipient: Address,
         deadline: u64,
         target_amount: i128,
         token: Address,
-    ) {
-        assert!(
-            !e.storage().instance().has(&DataKey::Recipient),
-            "already initialized"
-        );
+        admin: Address,
+        fee: Option<Fee>,
+    ) -> Result<(), Error> {
+        if e.storage().persistent().has(&DataKey::Recipient(0)) {
+            return Err(Error::AlreadyInitialized);
+        }
+        // The recipient's authorization is required by create_campaign
+        if admin != recipient {
+            admin.require_auth();
+        }
 
-        e.storage().instance().set(&DataKey::Recipient, &recipient);
-        e.storage()
-            .instance()
-            .set(&DataKey::RecipientClaimed, &false);
-        e.storage()
-            .instance()
-            .set(&DataKey::Started, &get_ledger_timestamp(&e));
-        e.storage().instance().set(&DataKey::Deadline, &deadline);
-        e.storage().instance().set(&DataKey::Target, &target_amount);
-        e.storage().instance().set(&DataKey::Token, &token);
+        e.storage().instance().set(&DataKey::Admin, &admin);
+        set_platform_fee(&e, fee)?;
+
+        let options = CampaignOptions::new(&e);
+        Self::create_campaign(
+            e,
+            recipient,
+            Deadline::Timestamp(deadline),
+            target_amount,
+            token,
+            options,
+        )?;
+        Ok(())
+    }
+
+    /// Create a new campaign and return its id. Ids are allocated sequentially
+    /// starting from 0. The recipient must authorize it.
+    pub fn create_campaign(
+        e: Env,
+        recipient: Address,
+        deadline: Deadline,
+        target_amount: i128,
+        token: Address,
+        options: CampaignOptions,
+    ) -> Result<u64, Error> {
+        recipient.require_auth();
+        if options.hard_cap != 0 && options.hard_cap < target_amount {
+            return Err(Error::InvalidOptions);
+        }
+        if options.soft_cap < 0 || options.soft_cap > target_amount {
+            return Err(Error::InvalidOptions);
+        }
+        if let Deadline::Timestamp(timestamp) = deadline {
+            if options.start != 0 && options.start >= timestamp {
+                return Err(Error::InvalidOptions);
+            }
+        }
+        let started = if options.start == 0 {
+            get_ledger_timestamp(&e)
+        } else {
+            options.start
+        };
+        if options.extension_approval_bps > 10_000 {
+            return Err(Error::InvalidOptions);
+        }
+        if options.signers.is_empty() != (options.threshold == 0)
+            || options.threshold > options.signers.len()
+            || options.signers.iter().any(|signer| {
+                options.signers.first_index_of(&signer) != options.signers.last_index_of(&signer)
+            })
+        {
+            return Err(Error::InvalidOptions);
+        }
+        if !options.payout.is_empty()
+            && options
+                .payout
+                .iter()
+                .map(|share| u64::from(share.bps))
+                .sum::<u64>()
+                != 10_000
+        {
+            return Err(Error::InvalidOptions);
+        }
+        let mut payees = Vec::new(&e);
+        for share in options.payout.iter() {
+            if share.bps == 0 || payees.contains(&share.address) {
+                return Err(Error::InvalidOptions);
+            }
+            payees.push_back(share.address);
+        }
+        // The destination signers approve is where the recipient's part goes
+        if !options.signers.is_empty() && !payees.is_empty() && !payees.contains(&recipient) {
+            return Err(Error::InvalidOptions);
+        }
+        if options.min_contribution < 0
+            || (options.max_per_donor != 0 && options.max_per_donor < options.min_contribution)
+        {
+            return Err(Error::InvalidOptions);
+        }
+        let campaign_id = get_campaign_count(&e);
+
+        set_persistent(&e, &DataKey::Recipient(campaign_id), &recipient);
+        set_persistent(&e, &DataKey::RecipientClaimed(campaign_id), &false);
+        set_persistent(&e, &DataKey::Cancelled(campaign_id), &false);
+        set_persistent(&e, &DataKey::ClosedEarly(campaign_id), &false);
+        set_persistent(&e, &DataKey::Started(campaign_id), &started);
+        set_deadline(&e, campaign_id, &deadline);
+        set_extended(&e, campaign_id, 0);
+        set_persistent(&e, &DataKey::Target(campaign_id), &target_amount);
+        set_persistent(&e, &DataKey::Token(campaign_id), &token);
+        set_persistent(&e, &DataKey::Options(campaign_id), &options);
+        // The campaign keeps the fee in force when it was created
+        if let Some(fee) = get_platform_fee(&e) {
+            set_persistent(&e, &DataKey::Fee(campaign_id), &fee);
+        }
+        set_total_pledged(&e, campaign_id, &0);
+        set_campaign_count(&e, campaign_id + 1);
+
+        Ok(campaign_id)
+    }
+
+    pub fn campaign_count(e: Env) -> u64 {
+        get_campaign_count(&e)
+    }
+
+    pub fn recipient(e: Env, campaign_id: u64) -> Result<Address, Error> {
+        get_recipient(&e, campaign_id)
+    }
+
+    pub fn deadline(e: Env, campaign_id: u64) -> Result<Deadline, Error> {
+        get_deadline(&e, campaign_id)
+    }
+
+    pub fn started(e: Env, campaign_id: u64) -> Result<u64, Error> {
+        get_started(&e, campaign_id)
+    }
+
+    pub fn state(e: Env, campaign_id: u64) -> Result<State, Error> {
+        get_state(&e, campaign_id)
+    }
+
+    pub fn target(e: Env, campaign_id: u64) -> Result<i128, Error> {
+        get_target_amount(&e, campaign_id)
+    }
+
+    pub fn soft_cap(e: Env, campaign_id: u64) -> Result<i128, Error> {
+        get_soft_cap(&e, campaign_id)
     }
 
-    pub fn recipient(e: Env) -> Address {
-        get_recipient(&e)
+    pub fn token(e: Env, campaign_id: u64) -> Result<Address, Error> {
+        get_token(&e, campaign_id)
     }
 
-    pub fn deadline(e: Env) -> u64 {
-        get_deadline(&e)
+    pub fn options(e: Env, campaign_id: u64) -> Result<CampaignOptions, Error> {
+        get_options(&e, campaign_id)
     }
 
-    pub fn started(e: Env) -> u64 {
-        get_started(&e)
+    pub fn min_contribution(e: Env, campaign_id: u64) -> Result<i128, Error> {
+        Ok(get_options(&e, campaign_id)?.min_contribution)
     }
 
-    pub fn state(e: Env) -> u32 {
-        get_state(&e) as u32
+    pub fn max_per_donor(e: Env, campaign_id: u64) -> Result<i128, Error> {
+        Ok(get_options(&e, campaign_id)?.max_per_donor)
     }
 
-    pub fn target(e: Env) -> i128 {
-        get_target_amount(&e)
+    /// The platform fee taken from the campaign when the recipient claims, if
+    /// any.
+    pub fn fee_info(e: Env, campaign_id: u64) -> Option<Fee> {
+        get_fee(&e, campaign_id)
     }
 
-    pub fn token(e: Env) -> Address {
-        get_token(&e)
+    pub fn total_pledged(e: Env, campaign_id: u64) -> Result<i128, Error> {
+        get_total_pledged(&e, campaign_id)
     }
 
-    pub fn balance(e: Env, user: Address) -> i128 {
-        let recipient = get_recipient(&e);
-        if get_state(&e) == State::Success {
-            if user != recipient {
-                return 0;
+    pub fn balance(e: Env, campaign_id: u64, user: Address) -> Result<i128, Error> {
+        if matches!(get_state(&e, campaign_id)?, State::Success | State::Claimed) {
+            if !is_recipient(&e, campaign_id, &user)? {
+                return Ok(0);
             };
-            return get_balance(&e, &get_token(&e));
+            return get_balance(&e, campaign_id);
         };
 
-        get_user_deposited(&e, &user)
+        Ok(get_user_deposited(&e, campaign_id, &user))
     }
 
-    pub fn deposit(e: Env, user: Address, amount: i128) {
+    /// Pledge `amount` from `user`, returning how much of it was accepted.
+    /// This is less than `amount` only if the deposit would take the campaign
+    /// past its hard cap and partial deposits are accepted.
+    pub fn deposit(e: Env, campaign_id: u64, user: Address, amount: i128) -> Result<i128, Error> {
         user.require_auth();
-        assert!(amount > 0, "amount must be positive");
-        assert!(get_state(&e) == State::Running, "sale is not running");
-        let token_id = get_token(&e);
-        let current_target_met = target_reached(&e, &token_id);
+        pledge(&e, campaign_id, Payer::Transfer(&user), &user, amount)
+    }
 
-        let recipient = get_recipient(&e);
-        assert!(user != recipient, "recipient may not deposit");
+    /// Pledge `amount` from `payer` on behalf of `beneficiary`, who owns the
+    /// pledge and any refund of it. Returns how much was accepted, like
+    /// `deposit`.
+    pub fn deposit_for(
+        e: Env,
+        campaign_id: u64,
+        payer: Address,
+        beneficiary: Address,
+        amount: i128,
+    ) -> Result<i128, Error> {
+        payer.require_auth();
+        pledge(
+            &e,
+            campaign_id,
+            Payer::Transfer(&payer),
+            &beneficiary,
+            amount,
+        )
+    }
 
-        let balance = get_user_deposited(&e, &user);
-        set_user_deposited(&e, &user, &(balance + amount));
+    /// Let `amount` of the allowance `user` granted this contract with the
+    /// token's `approve` be pledged to the campaign by anyone calling
+    /// `deposit_from_allowance`. Replaces any earlier approval for it.
+    pub fn approve_pledge(
+        e: Env,
+        campaign_id: u64,
+        user: Address,
+        amount: i128,
+    ) -> Result<(), Error> {
+        user.require_auth();
+        get_recipient(&e, campaign_id)?;
+        if amount < 0 {
+            return Err(Error::InvalidAmount);
+        }
+        set_pledge_approval(&e, campaign_id, &user, amount);
+        Ok(())
+    }
 
-        let client = token::Client::new(&e, &token_id);
-        client.transfer(&user, &e.current_contract_address(), &amount);
+    pub fn pledge_approval(e: Env, campaign_id: u64, user: Address) -> i128 {
+        get_pledge_approval(&e, campaign_id, &user)
+    }
 
-        let contract_balance = get_balance(&e, &token_id);
+    /// Pledge `amount` from `user` out of their allowance, up to what they
+    /// approved for the campaign with `approve_pledge`. Needs no authorization,
+    /// so a relayer may submit it. Returns how much was accepted, like
+    /// `deposit`.
+    pub fn deposit_from_allowance(
+        e: Env,
+        campaign_id: u64,
+        user: Address,
+        amount: i128,
+    ) -> Result<i128, Error> {
+        let approved = get_pledge_approval(&e, campaign_id, &user);
+        if approved < amount {
+            return Err(Error::InsufficientAllowance);
+        }
+        let accepted = pledge(&e, campaign_id, Payer::Allowance(&user), &user, amount)?;
+        set_pledge_approval(&e, campaign_id, &user, approved - accepted);
+        Ok(accepted)
+    }
+
+    /// Claim the payout of a successful campaign, if `to` is its recipient or
+    /// one of its payees, or refund `to`'s pledge to a failed one. `to` must
+    /// authorize it.
+    pub fn withdraw(e: Env, campaign_id: u64, to: Address) -> Result<(), Error> {
+        to.require_auth();
+        let destination = get_destination(&e, campaign_id, &to)?;
+        withdraw_for(&e, campaign_id, &to, &destination)
+    }
+
+    /// Withdraw like `withdraw`, but send `owner`'s payout or refund to
+    /// `destination` instead.
+    pub fn withdraw_to(
+        e: Env,
+        campaign_id: u64,
+        owner: Address,
+        destination: Address,
+    ) -> Result<(), Error> {
+        owner.require_auth();
+        withdraw_for(&e, campaign_id, &owner, &destination)
+    }
+
+    /// Send the recipient's payout to `address` rather than the recipient.
+    /// Campaigns with signers pay out where the approved proposal says, so
+    /// the recipient alone may not redirect it.
+    pub fn set_payout_address(e: Env, campaign_id: u64, address: Address) -> Result<(), Error> {
+        get_recipient(&e, campaign_id)?.require_auth();
+        if !get_options(&e, campaign_id)?.signers.is_empty() {
+            return Err(Error::ApprovalRequired);
+        }
+
+        set_persistent(&e, &DataKey::PayoutAddress(campaign_id), &address);
+        events::payout_address_changed(&e, campaign_id, address);
+        Ok(())
+    }
+
+    pub fn payout_address(e: Env, campaign_id: u64) -> Result<Address, Error> {
+        get_payout_address(&e, campaign_id)
+    }
+
+    /// Withdraw on behalf of `to` as one of the campaign's keepers, who may
+    /// trigger payouts and refunds without `to` authorizing them. The tokens
+    /// still go to `to`.
+    pub fn keeper_withdraw(
+        e: Env,
+        campaign_id: u64,
+        keeper: Address,
+        to: Address,
+    ) -> Result<(), Error> {
+        keeper.require_auth();
+        if !get_options(&e, campaign_id)?.keepers.contains(&keeper) {
+            return Err(Error::NotAKeeper);
+        }
+        let destination = get_destination(&e, campaign_id, &to)?;
+        withdraw_for(&e, campaign_id, &to, &destination)
+    }
+
+    /// Take back `amount` of `user`'s pledge while the campaign is running,
+    /// unless the campaign's options lock pledges at this point.
+    pub fn unpledge(e: Env, campaign_id: u64, user: Address, amount: i128) -> Result<(), Error> {
+        user.require_auth();
+        if amount <= 0 {
+            return Err(Error::InvalidAmount);
+        }
+        check_transition(get_state(&e, campaign_id)?, Action::Unpledge)?;
+        if unpledge_locked(&e, campaign_id)? {
+            return Err(Error::PledgesLocked);
+        }
+
+        let balance = get_user_deposited(&e, campaign_id, &user);
+        if amount > balance {
+            return Err(Error::InsufficientPledge);
+        }
+        let balance = balance - amount;
+        // A pledge may be taken back entirely, but not cut below the minimum
+        if balance > 0 && balance < get_options(&e, campaign_id)?.min_contribution {
+            return Err(Error::BelowMinContribution);
+        }
+        set_user_deposited(&e, campaign_id, &user, &balance);
+
+        let token_id = get_token(&e, campaign_id)?;
+        let total_pledged = get_total_pledged(&e, campaign_id)? - amount;
+        set_total_pledged(&e, campaign_id, &total_pledged);
+        add_reserved(&e, &token_id, -amount);
+
+        transfer(&e, &token_id, &user, &amount);
 
         // emit events
-        events::pledged_amount_changed(&e, contract_balance);
-        if !current_target_met && target_reached(&e, &token_id) {
-            // only emit the target reached event once on the pledge that triggers target to be met
-            events::target_reached(&e, contract_balance, get_target_amount(&e));
+        events::pledged_amount_changed(&e, campaign_id, total_pledged);
+        Ok(())
+    }
+
+    /// Call off a running campaign. Donors may withdraw their full pledge
+    /// straight away, and the recipient may not claim anything.
+    pub fn cancel(e: Env, campaign_id: u64, reason: String) -> Result<(), Error> {
+        get_recipient(&e, campaign_id)?.require_auth();
+        check_transition(get_state(&e, campaign_id)?, Action::Cancel)?;
+
+        set_cancelled(&e, campaign_id);
+        events::campaign_cancelled(&e, campaign_id, reason);
+        Ok(())
+    }
+
+    /// End a running campaign that has reached its target before the
+    /// deadline. It takes no more deposits, and the recipient may claim the
+    /// pledges straight away.
+    pub fn close_early(e: Env, campaign_id: u64) -> Result<(), Error> {
+        get_recipient(&e, campaign_id)?.require_auth();
+        check_transition(get_state(&e, campaign_id)?, Action::CloseEarly)?;
+        if !target_reached(&e, campaign_id)? {
+            return Err(Error::TargetNotReached);
         }
+
+        set_closed_early(&e, campaign_id);
+        events::campaign_closed(&e, campaign_id, get_total_pledged(&e, campaign_id)?);
+        Ok(())
     }
 
-    pub fn withdraw(e: Env, to: Address) {
-        let state = get_state(&e);
-        let recipient = get_recipient(&e);
+    /// Propose paying out a successful campaign, with the recipient's part
+    /// going to `destination`, as one of its signers. It is released once the
+    /// threshold of signers approve it with `approve_payout`. Proposals for
+    /// other destinations stay open alongside it.
+    pub fn propose_payout(
+        e: Env,
+        campaign_id: u64,
+        signer: Address,
+        destination: Address,
+    ) -> Result<(), Error> {
+        signer.require_auth();
+        check_transition(get_state(&e, campaign_id)?, Action::Claim)?;
+        if !get_options(&e, campaign_id)?.signers.contains(&signer) {
+            return Err(Error::NotASigner);
+        }
 
-        match state {
-            State::Running => {
-                panic!("sale is still running")
-            }
-            State::Success => {
-                assert!(
-                    to == recipient,
-                    "sale was successful, only the recipient may withdraw"
-                );
-                assert!(
-                    !get_recipient_claimed(&e),
-                    "sale was successful, recipient has withdrawn funds already"
-                );
-
-                let token = get_token(&e);
-                transfer(&e, &recipient, &get_balance(&e, &token));
-                set_recipient_claimed(&e);
-            }
-            State::Expired => {
-                assert!(
-                    to != recipient,
-                    "sale expired, the recipient may not withdraw"
-                );
-
-                // Withdraw full amount
-                let balance = get_user_deposited(&e, &to);
-                set_user_deposited(&e, &to, &0);
-                transfer(&e, &to, &balance);
-
-                // emit events
-                let token_id = get_token(&e);
-                let contract_balance = get_balance(&e, &token_id);
-                events::pledged_amount_changed(&e, contract_balance);
-            }
+        if get_payout_proposal(&e, campaign_id, &destination).is_some() {
+            return Err(Error::PayoutAlreadyProposed);
+        }
+
+        let proposal = PayoutProposal {
+            destination: destination.clone(),
+            approvers: Vec::new(&e),
+        };
+        set_payout_proposal(&e, campaign_id, &proposal);
+        events::payout_proposed(&e, campaign_id, signer, destination);
+        Ok(())
+    }
+
+    /// Approve the payout proposed to `destination` as one of the campaign's
+    /// signers. The payout is released once the threshold of signers has
+    /// approved it. Returns whether it was.
+    pub fn approve_payout(
+        e: Env,
+        campaign_id: u64,
+        signer: Address,
+        destination: Address,
+    ) -> Result<bool, Error> {
+        signer.require_auth();
+        check_transition(get_state(&e, campaign_id)?, Action::Claim)?;
+        let options = get_options(&e, campaign_id)?;
+        if !options.signers.contains(&signer) {
+            return Err(Error::NotASigner);
+        }
+        let mut proposal =
+            get_payout_proposal(&e, campaign_id, &destination).ok_or(Error::NoPayoutProposed)?;
+
+        if proposal.approvers.contains(&signer) {
+            return Err(Error::AlreadyApproved);
+        }
+        proposal.approvers.push_back(signer.clone());
+        set_payout_proposal(&e, campaign_id, &proposal);
+        events::payout_approved(&e, campaign_id, signer, proposal.approvers.len());
+
+        if proposal.approvers.len() < options.threshold {
+            return Ok(false);
+        }
+        let recipient = get_recipient(&e, campaign_id)?;
+        claim(&e, campaign_id, &recipient, &proposal.destination)?;
+        remove_payout_proposal(&e, campaign_id, &destination);
+        events::payout_released(&e, campaign_id, proposal.destination, proposal.approvers);
+        Ok(true)
+    }
+
+    pub fn payout_proposal(
+        e: Env,
+        campaign_id: u64,
+        destination: Address,
+    ) -> Option<PayoutProposal> {
+        get_payout_proposal(&e, campaign_id, &destination)
+    }
+
+    /// Propose moving the deadline of a running campaign out to `deadline`,
+    /// which must be later than the current one and of the same kind. It
+    /// takes effect once donors approve it with `vote_extension`, and replaces
+    /// any earlier proposal.
+    pub fn propose_extension(e: Env, campaign_id: u64, deadline: Deadline) -> Result<(), Error> {
+        get_recipient(&e, campaign_id)?.require_auth();
+        check_transition(get_state(&e, campaign_id)?, Action::Extend)?;
+        if get_options(&e, campaign_id)?.extension_approval_bps == 0 {
+            return Err(Error::ExtensionNotAllowed);
+        }
+        if !is_later(deadline, get_deadline(&e, campaign_id)?) {
+            return Err(Error::InvalidDeadline);
+        }
+
+        let proposal = ExtensionProposal {
+            id: next_extension_proposal(&e, campaign_id),
+            deadline,
+            approved: 0,
+        };
+        set_extension_proposal(&e, campaign_id, &proposal);
+        events::extension_proposed(&e, campaign_id, deadline);
+        Ok(())
+    }
+
+    /// Approve or reject the proposed extension on behalf of `user`, whose
+    /// approval counts as much as their pledge, as it changes. The deadline changes as soon as
+    /// the approvals exceed the campaign's approval share of the total
+    /// pledged, unless the deadline has since moved past the proposed one.
+    /// Returns whether it did.
+    pub fn vote_extension(
+        e: Env,
+        campaign_id: u64,
+        user: Address,
+        approve: bool,
+    ) -> Result<bool, Error> {
+        user.require_auth();
+        check_transition(get_state(&e, campaign_id)?, Action::Extend)?;
+        let mut proposal =
+            get_extension_proposal(&e, campaign_id).ok_or(Error::NoExtensionProposed)?;
+        let pledge = get_user_deposited(&e, campaign_id, &user);
+        if pledge == 0 {
+            return Err(Error::InsufficientPledge);
+        }
+
+        proposal.approved -= get_extension_vote(&e, campaign_id, &user, proposal.id);
+        let vote = if approve {
+            proposal.approved += pledge;
+            Some(ExtensionVote {
+                proposal: proposal.id,
+                pledge,
+            })
+        } else {
+            None
         };
+        set_extension_vote(&e, campaign_id, &user, vote);
+
+        if !extension_approved(&e, campaign_id, &proposal)? {
+            set_extension_proposal(&e, campaign_id, &proposal);
+            return Ok(false);
+        }
+        // Deposits near the deadline may have pushed it past the proposal since
+        if !is_later(proposal.deadline, get_deadline(&e, campaign_id)?) {
+            return Err(Error::InvalidDeadline);
+        }
+        set_deadline(&e, campaign_id, &proposal.deadline);
+        remove_extension_proposal(&e, campaign_id);
+        events::deadline_extended(&e, campaign_id, proposal.deadline);
+        Ok(true)
+    }
+
+    pub fn extension_proposal(e: Env, campaign_id: u64) -> Option<ExtensionProposal> {
+        get_extension_proposal(&e, campaign_id)
+    }
+
+    /// Hand the admin role over to `admin`. Only the current admin may call
+    /// this; the first admin is set by initialize.
+    pub fn set_admin(e: Env, admin: Address) -> Result<(), Error> {
+        get_admin(&e)?.require_auth();
+        e.storage().instance().set(&DataKey::Admin, &admin);
+        Ok(())
+    }
+
+    pub fn admin(e: Env) -> Result<Address, Error> {
+        get_admin(&e)
+    }
+
+    /// Set the fee campaigns created from now on take from their payout, or
+    /// remove it. Only the admin may call this.
+    pub fn set_fee(e: Env, fee: Option<Fee>) -> Result<(), Error> {
+        get_admin(&e)?.require_auth();
+        set_platform_fee(&e, fee)
+    }
+
+    pub fn fee(e: Env) -> Option<Fee> {
+        get_platform_fee(&e)
+    }
+
+    /// Send tokens that were transferred to the contract directly, rather than
+    /// pledged through `deposit`, to `to`. Such transfers do not belong to any
+    /// campaign, so only the admin may recover them. Returns the amount swept.
+    pub fn sweep_excess(e: Env, token: Address, to: Address) -> Result<i128, Error> {
+        get_admin(&e)?.require_auth();
+
+        let excess = get_excess(&e, &token);
+        if excess > 0 {
+            transfer(&e, &token, &to, &excess);
+            events::excess_swept(&e, token, to, excess);
+        }
+        Ok(excess)
     }
 }
@@ -1,9 +1,18 @@
 #![cfg(test)]
+extern crate std;
 
 use super::testutils::{register_test_contract as register_crowdfund, Crowdfund};
+use crate::{
+    CampaignOptions, DataKey, Deadline, Error, Fee, FundingMode, PayoutProposal, Share, State,
+    UserDataKey,
+};
 use soroban_sdk::{
-    testutils::{Address as AddressTestTrait, Events, Ledger},
-    token, vec, Address, Env, IntoVal, Symbol, Val, Vec,
+    symbol_short,
+    testutils::{
+        Address as AddressTestTrait, AuthorizedFunction, AuthorizedInvocation, Events, Ledger,
+        MockAuth, MockAuthInvoke,
+    },
+    token, vec, Address, Env, IntoVal, String, Symbol, Val, Vec,
 };
 
 fn create_crowdfund_contract(
@@ -12,12 +21,18 @@ fn create_crowdfund_contract(
     deadline: u64,
     target_amount: &i128,
     token: &Address,
+    admin: &Address,
 ) -> (Address, Crowdfund) {
     let id = register_crowdfund(e);
     let crowdfund = Crowdfund::new(e, id.clone());
-    crowdfund
-        .client()
-        .initialize(recipient, &deadline, target_amount, token);
+    crowdfund.client().mock_all_auths().initialize(
+        recipient,
+        &deadline,
+        target_amount,
+        token,
+        admin,
+        &None,
+    );
     (id, crowdfund)
 }
 
@@ -29,12 +44,14 @@ fn advance_ledger(e: &Env, delta: u64) {
 
 struct Setup<'a> {
     env: Env,
+    admin: Address,
     recipient: Address,
     user1: Address,
     user2: Address,
     token: token::Client<'a>,
     crowdfund: Crowdfund,
     crowdfund_id: Address,
+    campaign_id: u64,
 }
 
 fn create_token_contract<'a>(
@@ -56,6 +73,7 @@ fn create_token_contract<'a>(
 impl Setup<'_> {
     fn new() -> Self {
         let e: Env = soroban_sdk::Env::default();
+        let admin = Address::random(&e);
         let recipient = Address::random(&e);
         let user1 = Address::random(&e);
         let user2 = Address::random(&e);
@@ -69,23 +87,35 @@ impl Setup<'_> {
         let (token, token_admin) = create_token_contract(&e, &token_admin);
 
         // Create the crowdfunding contract
-        let (crowdfund_id, crowdfund) =
-            create_crowdfund_contract(&e, &recipient, deadline, &target_amount, &token.address);
+        let (crowdfund_id, crowdfund) = create_crowdfund_contract(
+            &e,
+            &recipient,
+            deadline,
+            &target_amount,
+            &token.address,
+            &admin,
+        );
 
         // Mint some tokens to work with
         token_admin.mock_all_auths().mint(&user1, &10);
         token_admin.mock_all_auths().mint(&user2, &8);
 
-        crowdfund.client().mock_all_auths().deposit(&user1, &10);
+        let campaign_id = 0;
+        crowdfund
+            .client()
+            .mock_all_auths()
+            .deposit(&campaign_id, &user1, &10);
 
         Self {
             env: e,
+            admin,
             recipient,
             user1,
             user2,
             token,
             crowdfund,
             crowdfund_id,
+            campaign_id,
         }
     }
 }
@@ -99,7 +129,7 @@ fn test_expired() {
         .crowdfund
         .client()
         .mock_all_auths()
-        .withdraw(&setup.user1);
+        .withdraw(&setup.campaign_id, &setup.user1);
 
     assert_eq!(setup.token.balance(&setup.user1), 10);
     assert_eq!(setup.token.balance(&setup.crowdfund_id), 0);
@@ -112,12 +142,12 @@ fn test_events() {
         .crowdfund
         .client()
         .mock_all_auths()
-        .deposit(&setup.user2, &5);
+        .deposit(&setup.campaign_id, &setup.user2, &5);
     setup
         .crowdfund
         .client()
         .mock_all_auths()
-        .deposit(&setup.user2, &3);
+        .deposit(&setup.campaign_id, &setup.user2, &3);
 
     let mut crowd_fund_events: Vec<(Address, soroban_sdk::Vec<Val>, Val)> = vec![&setup.env];
 
@@ -136,24 +166,24 @@ fn test_events() {
             &setup.env,
             (
                 setup.crowdfund_id.clone(),
-                (Symbol::new(&setup.env, "pledged_amount_changed"),).into_val(&setup.env),
+                (Symbol::new(&setup.env, "pledged_amount_changed"), 0_u64).into_val(&setup.env),
                 10_i128.into_val(&setup.env)
             ),
             (
                 setup.crowdfund_id.clone(),
-                (Symbol::new(&setup.env, "pledged_amount_changed"),).into_val(&setup.env),
+                (Symbol::new(&setup.env, "pledged_amount_changed"), 0_u64).into_val(&setup.env),
                 15_i128.into_val(&setup.env)
             ),
             (
                 // validate that this event only emitted once, ensuing deposits over the
                 // target before expiration, don't trigger this one again
                 setup.crowdfund_id.clone(),
-                (Symbol::new(&setup.env, "target_reached"),).into_val(&setup.env),
+                (Symbol::new(&setup.env, "target_reached"), 0_u64).into_val(&setup.env),
                 (15_i128, 15_i128).into_val(&setup.env)
             ),
             (
                 setup.crowdfund_id.clone(),
-                (Symbol::new(&setup.env, "pledged_amount_changed"),).into_val(&setup.env),
+                (Symbol::new(&setup.env, "pledged_amount_changed"), 0_u64).into_val(&setup.env),
                 18_i128.into_val(&setup.env)
             ),
         ]
@@ -167,7 +197,7 @@ fn test_success() {
         .crowdfund
         .client()
         .mock_all_auths()
-        .deposit(&setup.user2, &5);
+        .deposit(&setup.campaign_id, &setup.user2, &5);
 
     assert_eq!(setup.token.mock_all_auths().balance(&setup.user1), 0);
     assert_eq!(setup.token.mock_all_auths().balance(&setup.user2), 3);
@@ -175,13 +205,25 @@ fn test_success() {
         setup.token.mock_all_auths().balance(&setup.crowdfund_id),
         15
     );
+    assert_eq!(
+        setup.crowdfund.client().state(&setup.campaign_id),
+        State::Running
+    );
 
     advance_ledger(&setup.env, 10);
+    assert_eq!(
+        setup.crowdfund.client().state(&setup.campaign_id),
+        State::Success
+    );
     setup
         .crowdfund
         .client()
         .mock_all_auths()
-        .withdraw(&setup.recipient);
+        .withdraw(&setup.campaign_id, &setup.recipient);
+    assert_eq!(
+        setup.crowdfund.client().state(&setup.campaign_id),
+        State::Claimed
+    );
 
     assert_eq!(setup.token.mock_all_auths().balance(&setup.user1), 0);
     assert_eq!(setup.token.mock_all_auths().balance(&setup.user2), 3);
@@ -190,103 +232,1986 @@ fn test_success() {
 }
 
 #[test]
-#[should_panic(expected = "sale is still running")]
 fn sale_still_running() {
+    let setup = Setup::new();
+    assert_eq!(
+        setup
+            .crowdfund
+            .client()
+            .mock_all_auths()
+            .try_withdraw(&setup.campaign_id, &setup.recipient),
+        Err(Ok(Error::SaleStillRunning))
+    );
+}
+
+#[test]
+fn sale_successful_only_recipient() {
     let setup = Setup::new();
     setup
         .crowdfund
         .client()
         .mock_all_auths()
-        .withdraw(&setup.recipient);
+        .deposit(&setup.campaign_id, &setup.user2, &5);
+    advance_ledger(&setup.env, 10);
+
+    assert_eq!(
+        setup
+            .crowdfund
+            .client()
+            .mock_all_auths()
+            .try_withdraw(&setup.campaign_id, &setup.user1),
+        Err(Ok(Error::OnlyRecipientMayWithdraw))
+    );
 }
 
 #[test]
-#[should_panic(expected = "sale was successful, only the recipient may withdraw")]
-fn sale_successful_only_recipient() {
+fn sale_successful_non_recipient_still_denied_after_withdrawal() {
     let setup = Setup::new();
     setup
         .crowdfund
         .client()
         .mock_all_auths()
-        .deposit(&setup.user2, &5);
+        .deposit(&setup.campaign_id, &setup.user2, &5);
     advance_ledger(&setup.env, 10);
 
     setup
         .crowdfund
         .client()
         .mock_all_auths()
-        .withdraw(&setup.user1);
+        .withdraw(&setup.campaign_id, &setup.recipient);
+    assert_eq!(
+        setup
+            .crowdfund
+            .client()
+            .mock_all_auths()
+            .try_withdraw(&setup.campaign_id, &setup.user1),
+        Err(Ok(Error::OnlyRecipientMayWithdraw))
+    );
 }
 
 #[test]
-#[should_panic(expected = "sale was successful, only the recipient may withdraw")]
-fn sale_successful_non_recipient_still_denied_after_withdrawal() {
+fn sale_successful_recipient_withdraws_only_once() {
     let setup = Setup::new();
     setup
         .crowdfund
         .client()
-        .mock_all_auths()
-        .deposit(&setup.user2, &5);
+        .deposit(&setup.campaign_id, &setup.user2, &5);
     advance_ledger(&setup.env, 10);
 
     setup
         .crowdfund
         .client()
         .mock_all_auths()
-        .withdraw(&setup.recipient);
+        .withdraw(&setup.campaign_id, &setup.recipient);
+    assert_eq!(
+        setup
+            .crowdfund
+            .client()
+            .mock_all_auths()
+            .try_withdraw(&setup.campaign_id, &setup.recipient),
+        Err(Ok(Error::AlreadyClaimed))
+    );
+}
+
+#[test]
+fn sale_expired_recipient_not_allowed() {
+    let setup = Setup::new();
+    advance_ledger(&setup.env, 10);
+
     setup
         .crowdfund
         .client()
         .mock_all_auths()
-        .withdraw(&setup.user1);
+        .withdraw(&setup.campaign_id, &setup.user1);
+    assert_eq!(
+        setup
+            .crowdfund
+            .client()
+            .mock_all_auths()
+            .try_withdraw(&setup.campaign_id, &setup.recipient),
+        Err(Ok(Error::RecipientMayNotWithdraw))
+    );
 }
 
 #[test]
-#[should_panic(expected = "sale was successful, recipient has withdrawn funds already")]
-fn sale_successful_recipient_withdraws_only_once() {
+fn sale_not_running() {
     let setup = Setup::new();
-    setup.crowdfund.client().deposit(&setup.user2, &5);
     advance_ledger(&setup.env, 10);
 
-    setup
-        .crowdfund
-        .client()
+    assert_eq!(
+        setup
+            .crowdfund
+            .client()
+            .mock_all_auths()
+            .try_deposit(&setup.campaign_id, &setup.user1, &1),
+        Err(Ok(Error::SaleNotRunning))
+    );
+}
+
+#[test]
+fn deposit_invalid() {
+    let setup = Setup::new();
+    let client = setup.crowdfund.client();
+
+    assert_eq!(
+        client
+            .mock_all_auths()
+            .try_deposit(&setup.campaign_id, &setup.user2, &0),
+        Err(Ok(Error::InvalidAmount))
+    );
+    assert_eq!(
+        client
+            .mock_all_auths()
+            .try_deposit(&setup.campaign_id, &setup.recipient, &1),
+        Err(Ok(Error::RecipientMayNotDeposit))
+    );
+    assert_eq!(
+        client.mock_all_auths().try_deposit(&1, &setup.user2, &1),
+        Err(Ok(Error::NotInitialized))
+    );
+    assert_eq!(client.try_state(&1), Err(Ok(Error::NotInitialized)));
+}
+
+#[test]
+fn test_campaigns_are_independent() {
+    let setup = Setup::new();
+    let client = setup.crowdfund.client();
+
+    // A second campaign on the same contract, sharing the token and deadline
+    let deadline = client.deadline(&setup.campaign_id);
+    let options = CampaignOptions::new(&setup.env);
+    let campaign_id = client.mock_all_auths().create_campaign(
+        &setup.user1,
+        &deadline,
+        &5,
+        &setup.token.address,
+        &options,
+    );
+    assert_eq!(
+        setup.env.auths(),
+        std::vec![(
+            setup.user1.clone(),
+            AuthorizedInvocation {
+                function: AuthorizedFunction::Contract((
+                    setup.crowdfund_id.clone(),
+                    Symbol::new(&setup.env, "create_campaign"),
+                    (
+                        &setup.user1,
+                        deadline,
+                        5_i128,
+                        &setup.token.address,
+                        options
+                    )
+                        .into_val(&setup.env),
+                )),
+                sub_invocations: std::vec![]
+            }
+        )]
+    );
+    assert_eq!(campaign_id, 1);
+    assert_eq!(client.campaign_count(), 2);
+    assert_eq!(client.recipient(&campaign_id), setup.user1);
+
+    // Each campaign has entries of its own, outside the instance entry
+    setup.env.as_contract(&setup.crowdfund_id, || {
+        assert!(setup
+            .env
+            .storage()
+            .persistent()
+            .has(&DataKey::Recipient(campaign_id)));
+        assert!(!setup
+            .env
+            .storage()
+            .instance()
+            .has(&DataKey::Recipient(campaign_id)));
+    });
+
+    client
         .mock_all_auths()
-        .withdraw(&setup.recipient);
-    setup
-        .crowdfund
-        .client()
+        .deposit(&campaign_id, &setup.user2, &6);
+    assert_eq!(client.balance(&campaign_id, &setup.user2), 6);
+    assert_eq!(client.balance(&setup.campaign_id, &setup.user2), 0);
+    assert_eq!(setup.token.balance(&setup.crowdfund_id), 16);
+
+    advance_ledger(&setup.env, 10);
+
+    // Campaign 0 missed its target even though the contract holds more than
+    // 15 tokens in total, while campaign 1 was funded.
+    assert_eq!(client.state(&setup.campaign_id), State::Expired);
+    assert_eq!(client.state(&campaign_id), State::Success);
+
+    client
         .mock_all_auths()
-        .withdraw(&setup.recipient);
+        .withdraw(&setup.campaign_id, &setup.user1);
+    client.mock_all_auths().withdraw(&campaign_id, &setup.user1);
+
+    assert_eq!(setup.token.balance(&setup.user1), 16);
+    assert_eq!(setup.token.balance(&setup.crowdfund_id), 0);
 }
 
 #[test]
-#[should_panic(expected = "sale expired, the recipient may not withdraw")]
-fn sale_expired_recipient_not_allowed() {
+fn initialize_already_initialized() {
+    let setup = Setup::new();
+    let deadline = setup.env.ledger().timestamp() + 10;
+
+    assert_eq!(
+        setup.crowdfund.client().try_initialize(
+            &setup.recipient,
+            &deadline,
+            &15,
+            &setup.token.address,
+            &setup.recipient,
+            &None,
+        ),
+        Err(Ok(Error::AlreadyInitialized))
+    );
+}
+
+#[test]
+fn test_initialize_with_fee() {
+    let e = Env::default();
+    let recipient = Address::random(&e);
+    let admin = Address::random(&e);
+    let token = Address::random(&e);
+    let fee = Fee {
+        fee_bps: 100,
+        treasury: Address::random(&e),
+    };
+    let crowdfund = Crowdfund::new(&e, register_crowdfund(&e));
+    let client = crowdfund.client();
+
+    assert_eq!(
+        client.mock_all_auths().try_initialize(
+            &recipient,
+            &10,
+            &15,
+            &token,
+            &admin,
+            &Some(Fee {
+                fee_bps: 10_001,
+                treasury: admin.clone(),
+            }),
+        ),
+        Err(Ok(Error::InvalidOptions))
+    );
+
+    client
+        .mock_all_auths()
+        .initialize(&recipient, &10, &15, &token, &admin, &Some(fee.clone()));
+    assert_eq!(client.admin(), admin);
+    assert_eq!(client.fee(), Some(fee.clone()));
+    assert_eq!(client.fee_info(&0), Some(fee));
+}
+
+#[test]
+fn test_donors_in_persistent_storage() {
+    let setup = Setup::new();
+    let key = DataKey::User(UserDataKey {
+        campaign_id: setup.campaign_id,
+        user: setup.user1.clone(),
+    });
+
+    setup.env.as_contract(&setup.crowdfund_id, || {
+        assert_eq!(
+            setup.env.storage().persistent().get::<_, i128>(&key),
+            Some(10)
+        );
+        assert!(!setup.env.storage().instance().has(&key));
+    });
+}
+
+#[test]
+fn test_direct_transfers_do_not_count() {
     let setup = Setup::new();
+    let client = setup.crowdfund.client();
+
+    // Sending tokens without calling deposit would take the contract's token
+    // balance past the target
+    setup
+        .token
+        .mock_all_auths()
+        .transfer(&setup.user2, &setup.crowdfund_id, &5);
+    assert_eq!(setup.token.balance(&setup.crowdfund_id), 15);
+    assert_eq!(client.total_pledged(&setup.campaign_id), 10);
+
     advance_ledger(&setup.env, 10);
+    assert_eq!(client.state(&setup.campaign_id), State::Expired);
 
+    client
+        .mock_all_auths()
+        .withdraw(&setup.campaign_id, &setup.user1);
+    assert_eq!(setup.token.balance(&setup.user1), 10);
+
+    let excess = client
+        .mock_all_auths()
+        .sweep_excess(&setup.token.address, &setup.user2);
+    assert_eq!(excess, 5);
+    assert_eq!(setup.token.balance(&setup.user2), 8);
+    assert_eq!(setup.token.balance(&setup.crowdfund_id), 0);
+}
+
+#[test]
+fn test_sweep_excess_keeps_pledges() {
+    let setup = Setup::new();
+    let client = setup.crowdfund.client();
     setup
-        .crowdfund
-        .client()
+        .token
+        .mock_all_auths()
+        .transfer(&setup.user2, &setup.crowdfund_id, &3);
+
+    let excess = client
         .mock_all_auths()
-        .withdraw(&setup.user1);
+        .sweep_excess(&setup.token.address, &setup.recipient);
+    assert_eq!(
+        setup.env.auths(),
+        std::vec![(
+            setup.admin.clone(),
+            AuthorizedInvocation {
+                function: AuthorizedFunction::Contract((
+                    setup.crowdfund_id.clone(),
+                    Symbol::new(&setup.env, "sweep_excess"),
+                    (&setup.token.address, &setup.recipient).into_val(&setup.env),
+                )),
+                sub_invocations: std::vec![]
+            }
+        )]
+    );
+    assert_eq!(excess, 3);
+    assert_eq!(setup.token.balance(&setup.recipient), 3);
+    assert_eq!(setup.token.balance(&setup.crowdfund_id), 10);
+    assert_eq!(client.balance(&setup.campaign_id, &setup.user1), 10);
+}
+
+#[test]
+fn test_set_admin() {
+    let setup = Setup::new();
+    let client = setup.crowdfund.client();
+    assert_eq!(client.admin(), setup.admin);
+
+    // Only the current admin may hand over the role
+    let admin = Address::random(&setup.env);
+    client.mock_all_auths().set_admin(&admin);
+    assert_eq!(setup.env.auths()[0].0, setup.admin);
+    assert_eq!(client.admin(), admin);
+}
+
+#[test]
+fn set_admin_not_initialized() {
+    let e = Env::default();
+    let crowdfund = Crowdfund::new(&e, register_crowdfund(&e));
+    let client = crowdfund.client();
+
+    // Nobody can claim the role before initialize has named an admin
+    let admin = Address::random(&e);
+    assert_eq!(
+        client.mock_all_auths().try_set_admin(&admin),
+        Err(Ok(Error::NotInitialized))
+    );
+}
+
+#[test]
+#[should_panic(expected = "HostError: Error(Auth, InvalidAction)")]
+fn sweep_excess_recipient_not_allowed() {
+    let setup = Setup::new();
+    let client = setup.crowdfund.client();
     setup
-        .crowdfund
-        .client()
+        .token
         .mock_all_auths()
-        .withdraw(&setup.recipient);
+        .transfer(&setup.user2, &setup.crowdfund_id, &3);
+
+    // Any campaign's recipient used to be able to take tokens that were not
+    // pledged to their campaign
+    client
+        .mock_auths(&[MockAuth {
+            address: &setup.recipient,
+            invoke: &MockAuthInvoke {
+                contract: &setup.crowdfund_id,
+                fn_name: "sweep_excess",
+                args: (&setup.token.address, &setup.recipient).into_val(&setup.env),
+                sub_invokes: &[],
+            },
+        }])
+        .sweep_excess(&setup.token.address, &setup.recipient);
 }
 
 #[test]
-#[should_panic(expected = "sale is not running")]
-fn sale_not_running() {
+fn test_cancel() {
+    let setup = Setup::new();
+    let client = setup.crowdfund.client();
+    let reason = String::from_slice(&setup.env, "venue unavailable");
+
+    client.mock_all_auths().cancel(&setup.campaign_id, &reason);
+    assert_eq!(
+        setup.env.auths(),
+        std::vec![(
+            setup.recipient.clone(),
+            AuthorizedInvocation {
+                function: AuthorizedFunction::Contract((
+                    setup.crowdfund_id.clone(),
+                    symbol_short!("cancel"),
+                    (setup.campaign_id, reason.clone()).into_val(&setup.env),
+                )),
+                sub_invocations: std::vec![]
+            }
+        )]
+    );
+    let events = setup.env.events().all();
+    assert_eq!(
+        events.slice(events.len() - 1..),
+        vec![
+            &setup.env,
+            (
+                setup.crowdfund_id.clone(),
+                (Symbol::new(&setup.env, "campaign_cancelled"), 0_u64).into_val(&setup.env),
+                reason.into_val(&setup.env)
+            )
+        ]
+    );
+    assert_eq!(client.state(&setup.campaign_id), State::Cancelled);
+
+    assert_eq!(
+        client
+            .mock_all_auths()
+            .try_deposit(&setup.campaign_id, &setup.user2, &5),
+        Err(Ok(Error::SaleNotRunning))
+    );
+    assert_eq!(
+        client
+            .mock_all_auths()
+            .try_withdraw(&setup.campaign_id, &setup.recipient),
+        Err(Ok(Error::RecipientMayNotWithdraw))
+    );
+
+    // Donors do not have to wait for the deadline
+    client
+        .mock_all_auths()
+        .withdraw(&setup.campaign_id, &setup.user1);
+    assert_eq!(setup.token.balance(&setup.user1), 10);
+    assert_eq!(setup.token.balance(&setup.crowdfund_id), 0);
+}
+
+#[test]
+fn test_close_early() {
+    let setup = Setup::new();
+    let client = setup.crowdfund.client();
+
+    assert_eq!(
+        client.mock_all_auths().try_close_early(&setup.campaign_id),
+        Err(Ok(Error::TargetNotReached))
+    );
+    client
+        .mock_all_auths()
+        .deposit(&setup.campaign_id, &setup.user2, &5);
+
+    client.mock_all_auths().close_early(&setup.campaign_id);
+    assert_eq!(
+        setup.env.auths(),
+        std::vec![(
+            setup.recipient.clone(),
+            AuthorizedInvocation {
+                function: AuthorizedFunction::Contract((
+                    setup.crowdfund_id.clone(),
+                    Symbol::new(&setup.env, "close_early"),
+                    (setup.campaign_id,).into_val(&setup.env),
+                )),
+                sub_invocations: std::vec![]
+            }
+        )]
+    );
+    let events = setup.env.events().all();
+    assert_eq!(
+        events.slice(events.len() - 1..),
+        vec![
+            &setup.env,
+            (
+                setup.crowdfund_id.clone(),
+                (Symbol::new(&setup.env, "campaign_closed"), 0_u64).into_val(&setup.env),
+                15_i128.into_val(&setup.env)
+            )
+        ]
+    );
+    assert_eq!(client.state(&setup.campaign_id), State::Success);
+    assert_eq!(
+        client
+            .mock_all_auths()
+            .try_deposit(&setup.campaign_id, &setup.user2, &1),
+        Err(Ok(Error::SaleNotRunning))
+    );
+    assert_eq!(
+        client.mock_all_auths().try_close_early(&setup.campaign_id),
+        Err(Ok(Error::SaleNotRunning))
+    );
+
+    // The recipient does not have to wait for the deadline
+    client
+        .mock_all_auths()
+        .withdraw(&setup.campaign_id, &setup.recipient);
+    assert_eq!(setup.token.balance(&setup.recipient), 15);
+}
+
+#[test]
+fn cancel_not_running() {
     let setup = Setup::new();
+    let client = setup.crowdfund.client();
+    let reason = String::from_slice(&setup.env, "too late");
     advance_ledger(&setup.env, 10);
 
-    setup
-        .crowdfund
-        .client()
+    assert_eq!(
+        client
+            .mock_all_auths()
+            .try_cancel(&setup.campaign_id, &reason),
+        Err(Ok(Error::SaleNotRunning))
+    );
+}
+
+#[test]
+fn test_unpledge() {
+    let setup = Setup::new();
+    let client = setup.crowdfund.client();
+
+    client
+        .mock_all_auths()
+        .unpledge(&setup.campaign_id, &setup.user1, &4);
+    assert_eq!(
+        setup.env.auths(),
+        std::vec![(
+            setup.user1.clone(),
+            AuthorizedInvocation {
+                function: AuthorizedFunction::Contract((
+                    setup.crowdfund_id.clone(),
+                    symbol_short!("unpledge"),
+                    (setup.campaign_id, &setup.user1, 4_i128).into_val(&setup.env),
+                )),
+                sub_invocations: std::vec![]
+            }
+        )]
+    );
+    let events = setup.env.events().all();
+    assert_eq!(
+        events.slice(events.len() - 1..),
+        vec![
+            &setup.env,
+            (
+                setup.crowdfund_id.clone(),
+                (Symbol::new(&setup.env, "pledged_amount_changed"), 0_u64).into_val(&setup.env),
+                6_i128.into_val(&setup.env)
+            )
+        ]
+    );
+    assert_eq!(client.balance(&setup.campaign_id, &setup.user1), 6);
+    assert_eq!(client.total_pledged(&setup.campaign_id), 6);
+    assert_eq!(setup.token.balance(&setup.user1), 4);
+    assert_eq!(setup.token.balance(&setup.crowdfund_id), 6);
+
+    assert_eq!(
+        client
+            .mock_all_auths()
+            .try_unpledge(&setup.campaign_id, &setup.user1, &7),
+        Err(Ok(Error::InsufficientPledge))
+    );
+
+    advance_ledger(&setup.env, 10);
+    assert_eq!(
+        client
+            .mock_all_auths()
+            .try_unpledge(&setup.campaign_id, &setup.user1, &6),
+        Err(Ok(Error::SaleNotRunning))
+    );
+}
+
+#[test]
+fn unpledge_locked() {
+    let setup = Setup::new();
+    let client = setup.crowdfund.client();
+    let deadline = Deadline::Timestamp(setup.env.ledger().timestamp() + 100);
+
+    let options = CampaignOptions {
+        unpledge_locked_at_target: true,
+        ..CampaignOptions::new(&setup.env)
+    };
+    let campaign_id = client.create_campaign(
+        &setup.recipient,
+        &deadline,
+        &8,
+        &setup.token.address,
+        &options,
+    );
+    assert_eq!(client.options(&campaign_id), options);
+
+    client
+        .mock_all_auths()
+        .deposit(&campaign_id, &setup.user2, &7);
+    client
+        .mock_all_auths()
+        .unpledge(&campaign_id, &setup.user2, &1);
+    client
+        .mock_all_auths()
+        .deposit(&campaign_id, &setup.user2, &2);
+    assert_eq!(
+        client
+            .mock_all_auths()
+            .try_unpledge(&campaign_id, &setup.user2, &1),
+        Err(Ok(Error::PledgesLocked))
+    );
+
+    let options = CampaignOptions {
+        unpledge_lock_window: 20,
+        ..CampaignOptions::new(&setup.env)
+    };
+    let campaign_id = client.create_campaign(
+        &setup.recipient,
+        &deadline,
+        &8,
+        &setup.token.address,
+        &options,
+    );
+
+    // Move user1's pledge over to the second campaign
+    client
+        .mock_all_auths()
+        .unpledge(&setup.campaign_id, &setup.user1, &10);
+    client
+        .mock_all_auths()
+        .deposit(&campaign_id, &setup.user1, &5);
+    client
+        .mock_all_auths()
+        .unpledge(&campaign_id, &setup.user1, &1);
+
+    advance_ledger(&setup.env, 80);
+    assert_eq!(client.state(&campaign_id), State::Running);
+    assert_eq!(
+        client
+            .mock_all_auths()
+            .try_unpledge(&campaign_id, &setup.user1, &1),
+        Err(Ok(Error::PledgesLocked))
+    );
+}
+
+#[test]
+fn test_hard_cap() {
+    let setup = Setup::new();
+    let client = setup.crowdfund.client();
+    let deadline = Deadline::Timestamp(setup.env.ledger().timestamp() + 100);
+    let options = CampaignOptions {
+        hard_cap: 12,
+        ..CampaignOptions::new(&setup.env)
+    };
+    let campaign_id = client.create_campaign(
+        &setup.recipient,
+        &deadline,
+        &10,
+        &setup.token.address,
+        &options,
+    );
+
+    client
         .mock_all_auths()
-        .deposit(&setup.user1, &1);
+        .unpledge(&setup.campaign_id, &setup.user1, &10);
+    assert_eq!(
+        client
+            .mock_all_auths()
+            .deposit(&campaign_id, &setup.user1, &10),
+        10
+    );
+    // The target alone does not end the campaign before the deadline
+    assert_eq!(client.state(&campaign_id), State::Running);
+
+    assert_eq!(
+        client
+            .mock_all_auths()
+            .try_deposit(&campaign_id, &setup.user2, &3),
+        Err(Ok(Error::HardCapExceeded))
+    );
+    assert_eq!(
+        client
+            .mock_all_auths()
+            .deposit(&campaign_id, &setup.user2, &2),
+        2
+    );
+    assert_eq!(client.state(&campaign_id), State::Success);
+
+    // The recipient does not have to wait for the deadline
+    client
+        .mock_all_auths()
+        .withdraw(&campaign_id, &setup.recipient);
+    assert_eq!(setup.token.balance(&setup.recipient), 12);
+}
+
+#[test]
+fn test_hard_cap_partial_deposit() {
+    let setup = Setup::new();
+    let client = setup.crowdfund.client();
+    let deadline = Deadline::Timestamp(setup.env.ledger().timestamp() + 100);
+    let options = CampaignOptions {
+        hard_cap: 5,
+        accept_partial_deposits: true,
+        ..CampaignOptions::new(&setup.env)
+    };
+    let campaign_id = client.create_campaign(
+        &setup.recipient,
+        &deadline,
+        &5,
+        &setup.token.address,
+        &options,
+    );
+
+    assert_eq!(
+        client
+            .mock_all_auths()
+            .deposit(&campaign_id, &setup.user2, &8),
+        5
+    );
+    assert_eq!(setup.token.balance(&setup.user2), 3);
+    assert_eq!(client.balance(&campaign_id, &setup.user2), 0);
+    assert_eq!(client.balance(&campaign_id, &setup.recipient), 5);
+    assert_eq!(client.state(&campaign_id), State::Success);
+}
+
+#[test]
+fn hard_cap_below_target() {
+    let setup = Setup::new();
+    let options = CampaignOptions {
+        hard_cap: 14,
+        ..CampaignOptions::new(&setup.env)
+    };
+
+    assert_eq!(
+        setup.crowdfund.client().try_create_campaign(
+            &setup.recipient,
+            &Deadline::Timestamp(100),
+            &15,
+            &setup.token.address,
+            &options,
+        ),
+        Err(Ok(Error::InvalidOptions))
+    );
+}
+
+#[test]
+fn test_soft_cap() {
+    let setup = Setup::new();
+    let client = setup.crowdfund.client();
+    let deadline = client.deadline(&setup.campaign_id);
+    let options = CampaignOptions {
+        soft_cap: 5,
+        ..CampaignOptions::new(&setup.env)
+    };
+    let campaign_id = client.create_campaign(
+        &setup.recipient,
+        &deadline,
+        &15,
+        &setup.token.address,
+        &options,
+    );
+    assert_eq!(client.soft_cap(&campaign_id), 5);
+    assert_eq!(client.soft_cap(&setup.campaign_id), 15);
+
+    client
+        .mock_all_auths()
+        .deposit(&campaign_id, &setup.user2, &5);
+    let events = setup.env.events().all();
+    assert_eq!(
+        events.slice(events.len() - 1..),
+        vec![
+            &setup.env,
+            (
+                setup.crowdfund_id.clone(),
+                (Symbol::new(&setup.env, "soft_cap_reached"), campaign_id).into_val(&setup.env),
+                (5_i128, 5_i128).into_val(&setup.env)
+            ),
+        ]
+    );
+    assert_eq!(client.state(&campaign_id), State::Running);
+
+    advance_ledger(&setup.env, 10);
+
+    // The soft cap was reached but the target was not
+    assert_eq!(client.state(&campaign_id), State::Success);
+    client
+        .mock_all_auths()
+        .withdraw(&campaign_id, &setup.recipient);
+    assert_eq!(setup.token.balance(&setup.recipient), 5);
+}
+
+#[test]
+fn soft_cap_above_target() {
+    let setup = Setup::new();
+    let options = CampaignOptions {
+        soft_cap: 16,
+        ..CampaignOptions::new(&setup.env)
+    };
+
+    assert_eq!(
+        setup.crowdfund.client().try_create_campaign(
+            &setup.recipient,
+            &Deadline::Timestamp(100),
+            &15,
+            &setup.token.address,
+            &options,
+        ),
+        Err(Ok(Error::InvalidOptions))
+    );
+}
+
+#[test]
+fn test_ledger_deadline() {
+    let setup = Setup::new();
+    let client = setup.crowdfund.client();
+    let deadline = Deadline::Ledger(setup.env.ledger().sequence() + 10);
+    let campaign_id = client.create_campaign(
+        &setup.recipient,
+        &deadline,
+        &15,
+        &setup.token.address,
+        &CampaignOptions::new(&setup.env),
+    );
+    assert_eq!(client.deadline(&campaign_id), deadline);
+    client
+        .mock_all_auths()
+        .deposit(&campaign_id, &setup.user2, &5);
+
+    // Only the ledger sequence counts towards a ledger deadline
+    advance_ledger(&setup.env, 10);
+    assert_eq!(client.state(&setup.campaign_id), State::Expired);
+    assert_eq!(client.state(&campaign_id), State::Running);
+
+    setup.env.ledger().with_mut(|l| {
+        l.sequence_number += 10;
+    });
+    assert_eq!(client.state(&campaign_id), State::Expired);
+    client.mock_all_auths().withdraw(&campaign_id, &setup.user2);
+    assert_eq!(setup.token.balance(&setup.user2), 8);
+}
+
+#[test]
+fn test_deadline_extension() {
+    let setup = Setup::new();
+    let client = setup.crowdfund.client();
+    let deadline = setup.env.ledger().timestamp() + 100;
+    let options = CampaignOptions {
+        extension_window: 10,
+        extension_duration: 20,
+        max_extension: 30,
+        ..CampaignOptions::new(&setup.env)
+    };
+    let campaign_id = client.create_campaign(
+        &setup.recipient,
+        &Deadline::Timestamp(deadline),
+        &15,
+        &setup.token.address,
+        &options,
+    );
+
+    // Deposits before the window leave the deadline alone
+    client
+        .mock_all_auths()
+        .deposit(&campaign_id, &setup.user2, &1);
+    assert_eq!(client.deadline(&campaign_id), Deadline::Timestamp(deadline));
+
+    advance_ledger(&setup.env, 95);
+    client
+        .mock_all_auths()
+        .deposit(&campaign_id, &setup.user2, &1);
+    assert_eq!(
+        client.deadline(&campaign_id),
+        Deadline::Timestamp(deadline + 20)
+    );
+    let events = setup.env.events().all();
+    assert_eq!(
+        events.slice(events.len() - 1..),
+        vec![
+            &setup.env,
+            (
+                setup.crowdfund_id.clone(),
+                (Symbol::new(&setup.env, "deadline_extended"), campaign_id).into_val(&setup.env),
+                Deadline::Timestamp(deadline + 20).into_val(&setup.env)
+            ),
+        ]
+    );
+
+    // The total extension is capped
+    advance_ledger(&setup.env, 20);
+    client
+        .mock_all_auths()
+        .deposit(&campaign_id, &setup.user2, &1);
+    assert_eq!(
+        client.deadline(&campaign_id),
+        Deadline::Timestamp(deadline + 30)
+    );
+    advance_ledger(&setup.env, 10);
+    client
+        .mock_all_auths()
+        .deposit(&campaign_id, &setup.user2, &1);
+    assert_eq!(
+        client.deadline(&campaign_id),
+        Deadline::Timestamp(deadline + 30)
+    );
+
+    advance_ledger(&setup.env, 5);
+    assert_eq!(client.state(&campaign_id), State::Expired);
+}
+
+#[test]
+fn test_extension_vote() {
+    let setup = Setup::new();
+    let client = setup.crowdfund.client();
+    let deadline = Deadline::Timestamp(setup.env.ledger().timestamp() + 100);
+    let options = CampaignOptions {
+        extension_approval_bps: 5_000,
+        ..CampaignOptions::new(&setup.env)
+    };
+    let campaign_id = client.create_campaign(
+        &setup.recipient,
+        &deadline,
+        &15,
+        &setup.token.address,
+        &options,
+    );
+    client
+        .mock_all_auths()
+        .unpledge(&setup.campaign_id, &setup.user1, &4);
+    client
+        .mock_all_auths()
+        .deposit(&campaign_id, &setup.user1, &4);
+    client
+        .mock_all_auths()
+        .deposit(&campaign_id, &setup.user2, &4);
+
+    assert_eq!(
+        client
+            .mock_all_auths()
+            .try_vote_extension(&campaign_id, &setup.user1, &true),
+        Err(Ok(Error::NoExtensionProposed))
+    );
+    assert_eq!(
+        client
+            .mock_all_auths()
+            .try_propose_extension(&setup.campaign_id, &Deadline::Timestamp(200)),
+        Err(Ok(Error::ExtensionNotAllowed))
+    );
+    assert_eq!(
+        client
+            .mock_all_auths()
+            .try_propose_extension(&campaign_id, &Deadline::Ledger(200)),
+        Err(Ok(Error::InvalidDeadline))
+    );
+
+    let extended = Deadline::Timestamp(setup.env.ledger().timestamp() + 200);
+    client
+        .mock_all_auths()
+        .propose_extension(&campaign_id, &extended);
+
+    // Half of the total pledged is not enough
+    assert!(!client
+        .mock_all_auths()
+        .vote_extension(&campaign_id, &setup.user2, &true));
+    assert_eq!(client.deadline(&campaign_id), deadline);
+    assert!(client
+        .mock_all_auths()
+        .vote_extension(&campaign_id, &setup.user1, &true));
+    assert_eq!(client.deadline(&campaign_id), extended);
+    assert_eq!(client.extension_proposal(&campaign_id), None);
+    let events = setup.env.events().all();
+    assert_eq!(
+        events.slice(events.len() - 1..),
+        vec![
+            &setup.env,
+            (
+                setup.crowdfund_id.clone(),
+                (Symbol::new(&setup.env, "deadline_extended"), campaign_id).into_val(&setup.env),
+                extended.into_val(&setup.env)
+            ),
+        ]
+    );
+
+    advance_ledger(&setup.env, 100);
+    assert_eq!(client.state(&campaign_id), State::Running);
+}
+
+#[test]
+fn extension_votes_follow_pledges() {
+    let setup = Setup::new();
+    let client = setup.crowdfund.client();
+    let options = CampaignOptions {
+        extension_approval_bps: 5_000,
+        ..CampaignOptions::new(&setup.env)
+    };
+    let campaign_id = client.create_campaign(
+        &setup.recipient,
+        &Deadline::Timestamp(setup.env.ledger().timestamp() + 100),
+        &15,
+        &setup.token.address,
+        &options,
+    );
+    client
+        .mock_all_auths()
+        .unpledge(&setup.campaign_id, &setup.user1, &6);
+    client
+        .mock_all_auths()
+        .deposit(&campaign_id, &setup.user1, &4);
+    client
+        .mock_all_auths()
+        .deposit(&campaign_id, &setup.user2, &4);
+    client
+        .mock_all_auths()
+        .propose_extension(&campaign_id, &Deadline::Timestamp(200));
+
+    // Approving twice counts the pledge once
+    for _ in 0..2 {
+        assert!(!client
+            .mock_all_auths()
+            .vote_extension(&campaign_id, &setup.user2, &true));
+    }
+    assert_eq!(client.extension_proposal(&campaign_id).unwrap().approved, 4);
+
+    // Unpledging takes the approval with it
+    client
+        .mock_all_auths()
+        .unpledge(&campaign_id, &setup.user2, &4);
+    client
+        .mock_all_auths()
+        .deposit(&campaign_id, &setup.user1, &1);
+    assert_eq!(client.extension_proposal(&campaign_id).unwrap().approved, 0);
+    assert!(!client
+        .mock_all_auths()
+        .vote_extension(&campaign_id, &setup.user1, &false));
+
+    // Approvals do not carry over to a new proposal
+    client
+        .mock_all_auths()
+        .vote_extension(&campaign_id, &setup.user1, &true);
+    client
+        .mock_all_auths()
+        .propose_extension(&campaign_id, &Deadline::Timestamp(300));
+    let proposal = client.extension_proposal(&campaign_id).unwrap();
+    assert_eq!((proposal.id, proposal.approved), (1, 0));
+    client
+        .mock_all_auths()
+        .deposit(&campaign_id, &setup.user1, &1);
+    assert_eq!(client.extension_proposal(&campaign_id).unwrap().approved, 0);
+}
+
+#[test]
+fn test_extension_vote_after_automatic_extension() {
+    let setup = Setup::new();
+    let client = setup.crowdfund.client();
+    let deadline = setup.env.ledger().timestamp() + 100;
+    let options = CampaignOptions {
+        extension_window: 10,
+        extension_duration: 50,
+        max_extension: 50,
+        extension_approval_bps: 5_000,
+        ..CampaignOptions::new(&setup.env)
+    };
+    let campaign_id = client.create_campaign(
+        &setup.recipient,
+        &Deadline::Timestamp(deadline),
+        &15,
+        &setup.token.address,
+        &options,
+    );
+    client
+        .mock_all_auths()
+        .deposit(&campaign_id, &setup.user2, &4);
+    client
+        .mock_all_auths()
+        .propose_extension(&campaign_id, &Deadline::Timestamp(deadline + 20));
+
+    // A deposit close to the deadline pushes it past the proposed one
+    advance_ledger(&setup.env, 95);
+    client
+        .mock_all_auths()
+        .deposit(&campaign_id, &setup.user2, &4);
+    assert_eq!(
+        client.deadline(&campaign_id),
+        Deadline::Timestamp(deadline + 50)
+    );
+
+    // Passing the proposal now would move the deadline back
+    assert_eq!(
+        client
+            .mock_all_auths()
+            .try_vote_extension(&campaign_id, &setup.user2, &true),
+        Err(Ok(Error::InvalidDeadline))
+    );
+    assert_eq!(
+        client.deadline(&campaign_id),
+        Deadline::Timestamp(deadline + 50)
+    );
+}
+
+#[test]
+fn test_fee() {
+    let setup = Setup::new();
+    let client = setup.crowdfund.client();
+    let treasury = Address::random(&setup.env);
+    let fee = Fee {
+        fee_bps: 2_500,
+        treasury: treasury.clone(),
+    };
+    assert_eq!(
+        client.mock_all_auths().try_set_fee(&Some(Fee {
+            fee_bps: 10_001,
+            treasury: treasury.clone(),
+        })),
+        Err(Ok(Error::InvalidOptions))
+    );
+    client.mock_all_auths().set_fee(&Some(fee.clone()));
+    assert_eq!(setup.env.auths()[0].0, setup.admin);
+    assert_eq!(client.fee(), Some(fee.clone()));
+
+    let campaign_id = client.create_campaign(
+        &setup.recipient,
+        &client.deadline(&setup.campaign_id),
+        &5,
+        &setup.token.address,
+        &CampaignOptions::new(&setup.env),
+    );
+    assert_eq!(client.fee_info(&campaign_id), Some(fee.clone()));
+    assert_eq!(client.fee_info(&setup.campaign_id), None);
+
+    // Campaigns keep the fee they were created with
+    client.mock_all_auths().set_fee(&None);
+    assert_eq!(client.fee(), None);
+    assert_eq!(client.fee_info(&campaign_id), Some(fee));
+    client
+        .mock_all_auths()
+        .deposit(&campaign_id, &setup.user2, &8);
+
+    advance_ledger(&setup.env, 10);
+
+    client
+        .mock_all_auths()
+        .withdraw(&campaign_id, &setup.recipient);
+    assert_eq!(setup.token.balance(&treasury), 2);
+    assert_eq!(setup.token.balance(&setup.recipient), 6);
+    let events = setup.env.events().all();
+    assert_eq!(
+        events.slice(events.len() - 1..),
+        vec![
+            &setup.env,
+            (
+                setup.crowdfund_id.clone(),
+                (Symbol::new(&setup.env, "claimed"), campaign_id).into_val(&setup.env),
+                (
+                    setup.recipient.clone(),
+                    setup.recipient.clone(),
+                    6_i128,
+                    2_i128
+                )
+                    .into_val(&setup.env)
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_split_payout() {
+    let setup = Setup::new();
+    let client = setup.crowdfund.client();
+    let payee = Address::random(&setup.env);
+    let mut options = CampaignOptions::new(&setup.env);
+    options.payout = vec![
+        &setup.env,
+        Share {
+            address: setup.recipient.clone(),
+            bps: 6_000,
+        },
+        Share {
+            address: payee.clone(),
+            bps: 3_000,
+        },
+    ];
+    assert_eq!(
+        client.try_create_campaign(
+            &setup.recipient,
+            &client.deadline(&setup.campaign_id),
+            &5,
+            &setup.token.address,
+            &options,
+        ),
+        Err(Ok(Error::InvalidOptions))
+    );
+
+    options.payout.set(
+        1,
+        Share {
+            address: payee.clone(),
+            bps: 4_000,
+        },
+    );
+    let campaign_id = client.create_campaign(
+        &setup.recipient,
+        &client.deadline(&setup.campaign_id),
+        &5,
+        &setup.token.address,
+        &options,
+    );
+    assert_eq!(
+        client
+            .mock_all_auths()
+            .try_deposit(&campaign_id, &payee, &1),
+        Err(Ok(Error::RecipientMayNotDeposit))
+    );
+    client
+        .mock_all_auths()
+        .deposit(&campaign_id, &setup.user2, &7);
+
+    advance_ledger(&setup.env, 10);
+
+    // Any payee may withdraw the payout for all of them
+    client.mock_all_auths().withdraw(&campaign_id, &payee);
+    assert_eq!(setup.token.balance(&setup.recipient), 4);
+    assert_eq!(setup.token.balance(&payee), 3);
+    assert_eq!(client.state(&campaign_id), State::Claimed);
+}
+
+#[test]
+fn split_payout_invalid_shares() {
+    let setup = Setup::new();
+    let client = setup.crowdfund.client();
+    let payee = Address::random(&setup.env);
+    let share = |address: &Address, bps: u32| Share {
+        address: address.clone(),
+        bps,
+    };
+    let invalid = [
+        // Wraps around to 10_000 in a u32
+        vec![
+            &setup.env,
+            share(&setup.recipient, u32::MAX),
+            share(&payee, 10_001),
+        ],
+        vec![
+            &setup.env,
+            share(&setup.recipient, 10_000),
+            share(&payee, 0),
+        ],
+        vec![&setup.env, share(&payee, 5_000), share(&payee, 5_000)],
+    ];
+
+    for payout in invalid {
+        let options = CampaignOptions {
+            payout,
+            ..CampaignOptions::new(&setup.env)
+        };
+        assert_eq!(
+            client.try_create_campaign(
+                &setup.recipient,
+                &client.deadline(&setup.campaign_id),
+                &5,
+                &setup.token.address,
+                &options,
+            ),
+            Err(Ok(Error::InvalidOptions))
+        );
+    }
+}
+
+fn create_multisig_campaign(setup: &Setup, signer: &Address) -> u64 {
+    let client = setup.crowdfund.client();
+    let mut options = CampaignOptions::new(&setup.env);
+    options.signers = vec![
+        &setup.env,
+        setup.recipient.clone(),
+        signer.clone(),
+        Address::random(&setup.env),
+    ];
+    options.threshold = 2;
+    let campaign_id = client.create_campaign(
+        &setup.recipient,
+        &client.deadline(&setup.campaign_id),
+        &5,
+        &setup.token.address,
+        &options,
+    );
+    client
+        .mock_all_auths()
+        .deposit(&campaign_id, &setup.user2, &8);
+    advance_ledger(&setup.env, 10);
+    campaign_id
+}
+
+#[test]
+fn test_payout_proposal() {
+    let setup = Setup::new();
+    let client = setup.crowdfund.client();
+    let signer = Address::random(&setup.env);
+    let treasury = Address::random(&setup.env);
+    let campaign_id = create_multisig_campaign(&setup, &signer);
+
+    assert_eq!(
+        client
+            .mock_all_auths()
+            .try_withdraw(&campaign_id, &setup.recipient),
+        Err(Ok(Error::ApprovalRequired))
+    );
+    assert_eq!(
+        client
+            .mock_all_auths()
+            .try_approve_payout(&campaign_id, &signer, &treasury),
+        Err(Ok(Error::NoPayoutProposed))
+    );
+    assert_eq!(
+        client
+            .mock_all_auths()
+            .try_propose_payout(&campaign_id, &setup.user2, &setup.user2),
+        Err(Ok(Error::NotASigner))
+    );
+
+    client
+        .mock_all_auths()
+        .propose_payout(&campaign_id, &signer, &treasury);
+    assert_eq!(
+        setup.env.auths(),
+        std::vec![(
+            signer.clone(),
+            AuthorizedInvocation {
+                function: AuthorizedFunction::Contract((
+                    setup.crowdfund_id.clone(),
+                    Symbol::new(&setup.env, "propose_payout"),
+                    (campaign_id, signer.clone(), treasury.clone()).into_val(&setup.env),
+                )),
+                sub_invocations: std::vec![]
+            }
+        )]
+    );
+    let events = setup.env.events().all();
+    assert_eq!(
+        events.slice(events.len() - 1..),
+        vec![
+            &setup.env,
+            (
+                setup.crowdfund_id.clone(),
+                (Symbol::new(&setup.env, "payout_proposed"), campaign_id).into_val(&setup.env),
+                (signer.clone(), treasury.clone()).into_val(&setup.env)
+            ),
+        ]
+    );
+    assert_eq!(
+        client.payout_proposal(&campaign_id, &treasury),
+        Some(PayoutProposal {
+            destination: treasury.clone(),
+            approvers: vec![&setup.env],
+        })
+    );
+
+    // Another proposal leaves this one and its approvals in place
+    client
+        .mock_all_auths()
+        .approve_payout(&campaign_id, &signer, &treasury);
+    assert_eq!(
+        client
+            .mock_all_auths()
+            .try_propose_payout(&campaign_id, &setup.recipient, &treasury),
+        Err(Ok(Error::PayoutAlreadyProposed))
+    );
+    client
+        .mock_all_auths()
+        .propose_payout(&campaign_id, &setup.recipient, &setup.recipient);
+    assert_eq!(
+        client
+            .payout_proposal(&campaign_id, &treasury)
+            .unwrap()
+            .approvers,
+        vec![&setup.env, signer]
+    );
+}
+
+#[test]
+fn test_payout_approval() {
+    let setup = Setup::new();
+    let client = setup.crowdfund.client();
+    let signer = Address::random(&setup.env);
+    let treasury = Address::random(&setup.env);
+    let campaign_id = create_multisig_campaign(&setup, &signer);
+    client
+        .mock_all_auths()
+        .propose_payout(&campaign_id, &signer, &treasury);
+
+    assert!(!client
+        .mock_all_auths()
+        .approve_payout(&campaign_id, &signer, &treasury));
+    assert_eq!(setup.env.auths()[0].0, signer);
+    let events = setup.env.events().all();
+    assert_eq!(
+        events.slice(events.len() - 1..),
+        vec![
+            &setup.env,
+            (
+                setup.crowdfund_id.clone(),
+                (Symbol::new(&setup.env, "payout_approved"), campaign_id).into_val(&setup.env),
+                (signer.clone(), 1_u32).into_val(&setup.env)
+            ),
+        ]
+    );
+    assert_eq!(
+        client
+            .mock_all_auths()
+            .try_approve_payout(&campaign_id, &signer, &treasury),
+        Err(Ok(Error::AlreadyApproved))
+    );
+    assert_eq!(setup.token.balance(&treasury), 0);
+
+    // The second approval releases the payout to the proposed destination
+    assert!(client
+        .mock_all_auths()
+        .approve_payout(&campaign_id, &setup.recipient, &treasury));
+    let events = setup.env.events().all();
+    assert_eq!(
+        events.slice(events.len() - 2..),
+        vec![
+            &setup.env,
+            (
+                setup.crowdfund_id.clone(),
+                (Symbol::new(&setup.env, "claimed"), campaign_id).into_val(&setup.env),
+                (setup.recipient.clone(), treasury.clone(), 8_i128, 0_i128).into_val(&setup.env)
+            ),
+            (
+                setup.crowdfund_id.clone(),
+                (Symbol::new(&setup.env, "payout_released"), campaign_id).into_val(&setup.env),
+                (
+                    treasury.clone(),
+                    vec![&setup.env, signer, setup.recipient.clone()]
+                )
+                    .into_val(&setup.env)
+            ),
+        ]
+    );
+    assert_eq!(setup.token.balance(&treasury), 8);
+    assert_eq!(client.state(&campaign_id), State::Claimed);
+    assert_eq!(client.payout_proposal(&campaign_id, &treasury), None);
+}
+
+#[test]
+fn signers_payout_without_recipient() {
+    let setup = Setup::new();
+    let payee = Address::random(&setup.env);
+    let mut options = CampaignOptions::new(&setup.env);
+    options.signers = vec![&setup.env, setup.recipient.clone()];
+    options.threshold = 1;
+    options.payout = vec![
+        &setup.env,
+        Share {
+            address: payee,
+            bps: 10_000,
+        },
+    ];
+
+    // There would be no part of the payout for the approved destination
+    assert_eq!(
+        setup.crowdfund.client().try_create_campaign(
+            &setup.recipient,
+            &Deadline::Timestamp(100),
+            &15,
+            &setup.token.address,
+            &options,
+        ),
+        Err(Ok(Error::InvalidOptions))
+    );
+}
+
+#[test]
+fn threshold_above_signers() {
+    let setup = Setup::new();
+    let mut options = CampaignOptions::new(&setup.env);
+    options.signers = vec![&setup.env, setup.recipient.clone()];
+    options.threshold = 2;
+
+    assert_eq!(
+        setup.crowdfund.client().try_create_campaign(
+            &setup.recipient,
+            &Deadline::Timestamp(100),
+            &15,
+            &setup.token.address,
+            &options,
+        ),
+        Err(Ok(Error::InvalidOptions))
+    );
+}
+
+#[test]
+fn test_withdraw_auth() {
+    let setup = Setup::new();
+    let client = setup.crowdfund.client();
+    advance_ledger(&setup.env, 11);
+
+    client
+        .mock_all_auths()
+        .withdraw(&setup.campaign_id, &setup.user1);
+    assert_eq!(
+        setup.env.auths(),
+        std::vec![(
+            setup.user1.clone(),
+            AuthorizedInvocation {
+                function: AuthorizedFunction::Contract((
+                    setup.crowdfund_id.clone(),
+                    symbol_short!("withdraw"),
+                    (setup.campaign_id, setup.user1.clone()).into_val(&setup.env),
+                )),
+                sub_invocations: std::vec![]
+            }
+        )]
+    );
+}
+
+#[test]
+#[should_panic(expected = "HostError: Error(Auth, InvalidAction)")]
+fn withdraw_unauthorized() {
+    let setup = Setup::new();
+    advance_ledger(&setup.env, 11);
+
+    // Nobody may refund user1 without their authorization
+    setup.env.set_auths(&[]);
+
+    setup
+        .crowdfund
+        .client()
+        .withdraw(&setup.campaign_id, &setup.user1);
+}
+
+#[test]
+fn test_keeper_withdraw() {
+    let setup = Setup::new();
+    let client = setup.crowdfund.client();
+    let keeper = Address::random(&setup.env);
+    let mut options = CampaignOptions::new(&setup.env);
+    options.keepers = vec![&setup.env, keeper.clone()];
+    let campaign_id = client.create_campaign(
+        &setup.recipient,
+        &client.deadline(&setup.campaign_id),
+        &15,
+        &setup.token.address,
+        &options,
+    );
+    client
+        .mock_all_auths()
+        .deposit(&campaign_id, &setup.user2, &5);
+    advance_ledger(&setup.env, 10);
+
+    assert_eq!(
+        client
+            .mock_all_auths()
+            .try_keeper_withdraw(&campaign_id, &setup.user1, &setup.user2),
+        Err(Ok(Error::NotAKeeper))
+    );
+
+    // Only the keeper authorizes the refund, which still goes to the donor
+    client
+        .mock_all_auths()
+        .keeper_withdraw(&campaign_id, &keeper, &setup.user2);
+    assert_eq!(
+        setup.env.auths(),
+        std::vec![(
+            keeper.clone(),
+            AuthorizedInvocation {
+                function: AuthorizedFunction::Contract((
+                    setup.crowdfund_id.clone(),
+                    Symbol::new(&setup.env, "keeper_withdraw"),
+                    (campaign_id, keeper.clone(), setup.user2.clone()).into_val(&setup.env),
+                )),
+                sub_invocations: std::vec![]
+            }
+        )]
+    );
+    assert_eq!(setup.token.balance(&setup.user2), 8);
+    assert_eq!(setup.token.balance(&keeper), 0);
+}
+
+#[test]
+fn test_withdraw_to() {
+    let setup = Setup::new();
+    let client = setup.crowdfund.client();
+    let destination = Address::random(&setup.env);
+    advance_ledger(&setup.env, 11);
+
+    client
+        .mock_all_auths()
+        .withdraw_to(&setup.campaign_id, &setup.user1, &destination);
+    assert_eq!(
+        setup.env.auths(),
+        std::vec![(
+            setup.user1.clone(),
+            AuthorizedInvocation {
+                function: AuthorizedFunction::Contract((
+                    setup.crowdfund_id.clone(),
+                    Symbol::new(&setup.env, "withdraw_to"),
+                    (setup.campaign_id, setup.user1.clone(), destination.clone())
+                        .into_val(&setup.env),
+                )),
+                sub_invocations: std::vec![]
+            }
+        )]
+    );
+    let events = setup.env.events().all();
+    assert_eq!(
+        events.slice(events.len() - 1..),
+        vec![
+            &setup.env,
+            (
+                setup.crowdfund_id.clone(),
+                (Symbol::new(&setup.env, "refunded"), 0_u64).into_val(&setup.env),
+                (setup.user1.clone(), destination.clone(), 10_i128).into_val(&setup.env)
+            ),
+        ]
+    );
+    assert_eq!(setup.token.balance(&destination), 10);
+    assert_eq!(setup.token.balance(&setup.user1), 0);
+    assert_eq!(client.balance(&setup.campaign_id, &setup.user1), 0);
+}
+
+#[test]
+fn test_payout_address() {
+    let setup = Setup::new();
+    let client = setup.crowdfund.client();
+    let payout_address = Address::random(&setup.env);
+    assert_eq!(client.payout_address(&setup.campaign_id), setup.recipient);
+
+    client
+        .mock_all_auths()
+        .set_payout_address(&setup.campaign_id, &payout_address);
+    assert_eq!(client.payout_address(&setup.campaign_id), payout_address);
+    client
+        .mock_all_auths()
+        .deposit(&setup.campaign_id, &setup.user2, &5);
+    advance_ledger(&setup.env, 10);
+
+    client
+        .mock_all_auths()
+        .withdraw(&setup.campaign_id, &setup.recipient);
+    let events = setup.env.events().all();
+    assert_eq!(
+        events.slice(events.len() - 1..),
+        vec![
+            &setup.env,
+            (
+                setup.crowdfund_id.clone(),
+                (Symbol::new(&setup.env, "claimed"), 0_u64).into_val(&setup.env),
+                (
+                    setup.recipient.clone(),
+                    payout_address.clone(),
+                    15_i128,
+                    0_i128
+                )
+                    .into_val(&setup.env)
+            ),
+        ]
+    );
+    assert_eq!(setup.token.balance(&payout_address), 15);
+    assert_eq!(setup.token.balance(&setup.recipient), 0);
+}
+
+#[test]
+fn payout_address_with_signers() {
+    let setup = Setup::new();
+    let client = setup.crowdfund.client();
+    let signer = Address::random(&setup.env);
+    let campaign_id = create_multisig_campaign(&setup, &signer);
+
+    // The signers decide where the payout goes, not the recipient alone
+    assert_eq!(
+        client
+            .mock_all_auths()
+            .try_set_payout_address(&campaign_id, &setup.user2),
+        Err(Ok(Error::ApprovalRequired))
+    );
+    assert_eq!(client.payout_address(&campaign_id), setup.recipient);
+}
+
+#[test]
+fn test_deposit_for() {
+    let setup = Setup::new();
+    let client = setup.crowdfund.client();
+    let beneficiary = Address::random(&setup.env);
+
+    assert_eq!(
+        client.mock_all_auths().try_deposit_for(
+            &setup.campaign_id,
+            &setup.user2,
+            &setup.recipient,
+            &5
+        ),
+        Err(Ok(Error::RecipientMayNotDeposit))
+    );
+
+    client
+        .mock_all_auths()
+        .deposit_for(&setup.campaign_id, &setup.user2, &beneficiary, &5);
+    assert_eq!(
+        setup.env.auths(),
+        std::vec![(
+            setup.user2.clone(),
+            AuthorizedInvocation {
+                function: AuthorizedFunction::Contract((
+                    setup.crowdfund_id.clone(),
+                    Symbol::new(&setup.env, "deposit_for"),
+                    (
+                        setup.campaign_id,
+                        setup.user2.clone(),
+                        beneficiary.clone(),
+                        5_i128
+                    )
+                        .into_val(&setup.env),
+                )),
+                sub_invocations: std::vec![AuthorizedInvocation {
+                    function: AuthorizedFunction::Contract((
+                        setup.token.address.clone(),
+                        symbol_short!("transfer"),
+                        (setup.user2.clone(), setup.crowdfund_id.clone(), 5_i128)
+                            .into_val(&setup.env),
+                    )),
+                    sub_invocations: std::vec![]
+                }]
+            }
+        )]
+    );
+    assert_eq!(setup.token.balance(&setup.user2), 3);
+    assert_eq!(client.balance(&setup.campaign_id, &beneficiary), 5);
+    assert_eq!(client.balance(&setup.campaign_id, &setup.user2), 0);
+
+    // The refund goes to the beneficiary, not the payer
+    client
+        .mock_all_auths()
+        .unpledge(&setup.campaign_id, &beneficiary, &5);
+    assert_eq!(setup.token.balance(&beneficiary), 5);
+    assert_eq!(setup.token.balance(&setup.user2), 3);
+}
+
+#[test]
+fn test_deposit_from_allowance() {
+    let setup = Setup::new();
+    let client = setup.crowdfund.client();
+    let expiration_ledger = setup.env.ledger().sequence() + 100;
+    setup
+        .token
+        .mock_all_auths()
+        .approve(&setup.user2, &setup.crowdfund_id, &5, &expiration_ledger);
+
+    // The donor sets aside part of the allowance for the campaign
+    client
+        .mock_all_auths()
+        .approve_pledge(&setup.campaign_id, &setup.user2, &5);
+    assert_eq!(
+        setup.env.auths(),
+        std::vec![(
+            setup.user2.clone(),
+            AuthorizedInvocation {
+                function: AuthorizedFunction::Contract((
+                    setup.crowdfund_id.clone(),
+                    Symbol::new(&setup.env, "approve_pledge"),
+                    (setup.campaign_id, &setup.user2, 5_i128).into_val(&setup.env),
+                )),
+                sub_invocations: std::vec![]
+            }
+        )]
+    );
+    assert_eq!(client.pledge_approval(&setup.campaign_id, &setup.user2), 5);
+
+    // Anyone may then submit the pledge
+    setup.env.set_auths(&[]);
+    assert_eq!(
+        client.deposit_from_allowance(&setup.campaign_id, &setup.user2, &4),
+        4
+    );
+    assert_eq!(setup.token.balance(&setup.user2), 4);
+    assert_eq!(setup.token.allowance(&setup.user2, &setup.crowdfund_id), 1);
+    assert_eq!(client.balance(&setup.campaign_id, &setup.user2), 4);
+    assert_eq!(client.pledge_approval(&setup.campaign_id, &setup.user2), 1);
+
+    assert_eq!(
+        client.try_deposit_from_allowance(&setup.campaign_id, &setup.user2, &2),
+        Err(Ok(Error::InsufficientAllowance))
+    );
+    assert_eq!(client.balance(&setup.campaign_id, &setup.user2), 4);
+}
+
+#[test]
+fn deposit_from_allowance_third_party() {
+    let setup = Setup::new();
+    let client = setup.crowdfund.client();
+    let attacker = Address::random(&setup.env);
+    let expiration_ledger = setup.env.ledger().sequence() + 100;
+    setup
+        .token
+        .mock_all_auths()
+        .approve(&setup.user2, &setup.crowdfund_id, &5, &expiration_ledger);
+    client
+        .mock_all_auths()
+        .approve_pledge(&setup.campaign_id, &setup.user2, &5);
+
+    // A campaign the attacker would collect from straight after its deadline
+    let options = CampaignOptions {
+        funding_mode: FundingMode::KeepItAll,
+        ..CampaignOptions::new(&setup.env)
+    };
+    let campaign_id = client.mock_all_auths().create_campaign(
+        &attacker,
+        &Deadline::Timestamp(setup.env.ledger().timestamp() + 1),
+        &15,
+        &setup.token.address,
+        &options,
+    );
+
+    // The approval only covers the campaign the donor chose
+    setup.env.set_auths(&[]);
+    assert_eq!(
+        client.try_deposit_from_allowance(&campaign_id, &setup.user2, &5),
+        Err(Ok(Error::InsufficientAllowance))
+    );
+    assert_eq!(setup.token.balance(&setup.user2), 8);
+}
+
+#[test]
+fn test_scheduled_start() {
+    let setup = Setup::new();
+    let client = setup.crowdfund.client();
+    let start = setup.env.ledger().timestamp() + 5;
+    let options = CampaignOptions {
+        start,
+        ..CampaignOptions::new(&setup.env)
+    };
+    let campaign_id = client.create_campaign(
+        &setup.recipient,
+        &Deadline::Timestamp(start + 10),
+        &15,
+        &setup.token.address,
+        &options,
+    );
+    assert_eq!(client.started(&campaign_id), start);
+    assert_eq!(client.state(&campaign_id), State::NotStarted);
+    assert_eq!(
+        client
+            .mock_all_auths()
+            .try_deposit(&campaign_id, &setup.user2, &5),
+        Err(Ok(Error::SaleNotRunning))
+    );
+
+    advance_ledger(&setup.env, 5);
+
+    assert_eq!(client.state(&campaign_id), State::Running);
+    client
+        .mock_all_auths()
+        .deposit(&campaign_id, &setup.user2, &5);
+    assert_eq!(client.balance(&campaign_id, &setup.user2), 5);
+}
+
+#[test]
+fn start_after_deadline() {
+    let setup = Setup::new();
+    let options = CampaignOptions {
+        start: 100,
+        ..CampaignOptions::new(&setup.env)
+    };
+
+    assert_eq!(
+        setup.crowdfund.client().try_create_campaign(
+            &setup.recipient,
+            &Deadline::Timestamp(100),
+            &15,
+            &setup.token.address,
+            &options,
+        ),
+        Err(Ok(Error::InvalidOptions))
+    );
+}
+
+#[test]
+fn test_contribution_limits() {
+    let setup = Setup::new();
+    let client = setup.crowdfund.client();
+    let deadline = Deadline::Timestamp(setup.env.ledger().timestamp() + 100);
+    let options = CampaignOptions {
+        min_contribution: 3,
+        max_per_donor: 6,
+        ..CampaignOptions::new(&setup.env)
+    };
+    let campaign_id = client.create_campaign(
+        &setup.recipient,
+        &deadline,
+        &10,
+        &setup.token.address,
+        &options,
+    );
+    assert_eq!(client.min_contribution(&campaign_id), 3);
+    assert_eq!(client.max_per_donor(&campaign_id), 6);
+
+    assert_eq!(
+        client
+            .mock_all_auths()
+            .try_deposit(&campaign_id, &setup.user2, &2),
+        Err(Ok(Error::BelowMinContribution))
+    );
+    client
+        .mock_all_auths()
+        .deposit(&campaign_id, &setup.user2, &3);
+
+    // The limits apply to each donor's total pledge
+    client
+        .mock_all_auths()
+        .deposit(&campaign_id, &setup.user2, &1);
+    assert_eq!(
+        client
+            .mock_all_auths()
+            .try_deposit(&campaign_id, &setup.user2, &3),
+        Err(Ok(Error::AboveMaxPerDonor))
+    );
+    client
+        .mock_all_auths()
+        .deposit(&campaign_id, &setup.user2, &2);
+    assert_eq!(client.balance(&campaign_id, &setup.user2), 6);
+
+    assert_eq!(
+        client
+            .mock_all_auths()
+            .try_unpledge(&campaign_id, &setup.user2, &4),
+        Err(Ok(Error::BelowMinContribution))
+    );
+    client
+        .mock_all_auths()
+        .unpledge(&campaign_id, &setup.user2, &6);
+}
+
+#[test]
+fn test_keep_it_all() {
+    let setup = Setup::new();
+    let client = setup.crowdfund.client();
+    let deadline = client.deadline(&setup.campaign_id);
+    let options = CampaignOptions {
+        funding_mode: FundingMode::KeepItAll,
+        ..CampaignOptions::new(&setup.env)
+    };
+    let campaign_id = client.create_campaign(
+        &setup.recipient,
+        &deadline,
+        &15,
+        &setup.token.address,
+        &options,
+    );
+    client
+        .mock_all_auths()
+        .deposit(&campaign_id, &setup.user2, &5);
+
+    advance_ledger(&setup.env, 10);
+
+    // Both campaigns missed the target, but only the all-or-nothing one
+    // refunds its donors
+    assert_eq!(client.state(&setup.campaign_id), State::Expired);
+    assert_eq!(client.state(&campaign_id), State::Success);
+    assert_eq!(client.balance(&campaign_id, &setup.user2), 0);
+    assert_eq!(client.balance(&campaign_id, &setup.recipient), 5);
+    assert_eq!(
+        client
+            .mock_all_auths()
+            .try_withdraw(&campaign_id, &setup.user2),
+        Err(Ok(Error::OnlyRecipientMayWithdraw))
+    );
+
+    client
+        .mock_all_auths()
+        .withdraw(&campaign_id, &setup.recipient);
+    client
+        .mock_all_auths()
+        .withdraw(&setup.campaign_id, &setup.user1);
+    assert_eq!(setup.token.balance(&setup.recipient), 5);
+    assert_eq!(setup.token.balance(&setup.user1), 10);
 }
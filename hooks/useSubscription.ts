@@ -51,8 +51,10 @@ export function useSubscription(
           filters: [
             {
               contractIds: [contractId],
+              // the second topic is the campaign id
               topics: [[
-                xdr.ScVal.scvSymbol(topic).toXDR("base64")
+                xdr.ScVal.scvSymbol(topic).toXDR("base64"),
+                "*"
               ]],
               type: "contract"
             }  
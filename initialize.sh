@@ -121,5 +121,6 @@ soroban contract invoke \
   --recipient "$ABUNDANCE_ADMIN_ADDRESS" \
   --deadline "$deadline" \
   --target_amount "1000000000" \
-  --token "$ABUNDANCE_ID"
+  --token "$ABUNDANCE_ID" \
+  --admin "$ABUNDANCE_ADMIN_ADDRESS"
 echo "Done"
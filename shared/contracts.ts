@@ -14,4 +14,10 @@ export const crowdfund = new Crowdfund.Contract({
   ...Crowdfund.networks[network as keyof typeof Crowdfund.networks],
 })
 
+/**
+ * The dapp tracks the campaign set up by `initialize.sh`, which is always
+ * campaign 0 of the deployed crowdfund contract.
+ */
+export const campaignId = BigInt(0)
+
 export const server = new Server(rpcUrl, { allowHttp: rpcUrl.startsWith('http:') })